@@ -1,32 +1,577 @@
 use std::path::Path;
 
-use swc_core::common::DUMMY_SP;
+use swc_core::common::errors::HANDLER;
+use swc_core::common::{Span, DUMMY_SP};
 use swc_core::ecma::ast::{
-    BlockStmtOrExpr, Expr, Ident, JSXAttr, JSXAttrName, JSXAttrOrSpread, JSXAttrValue,
-    JSXElementName, JSXExpr, JSXExprContainer, JSXOpeningElement, Lit, Stmt, Str,
+    ArrayLit, BinExpr, BinaryOp, BindingIdent, CallExpr, Callee, ComputedPropName, Decl,
+    ExportDecl, Expr, ExprOrSpread, Ident, ImportSpecifier, JSXAttr, JSXAttrName, JSXAttrOrSpread,
+    JSXAttrValue, JSXElement, JSXElementName, JSXExpr, JSXExprContainer, JSXMemberExpr, JSXObject,
+    JSXOpeningElement, KeyValueProp, Lit, MemberExpr, MemberProp, Module, ModuleDecl, ModuleItem,
+    ObjectLit, Pat, Prop, PropName, PropOrSpread, Str, VarDecl, VarDeclKind, VarDeclarator,
 };
-use swc_core::ecma::atoms::js_word;
+use swc_core::ecma::atoms::{js_word, Atom};
 use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 
+use crate::config::{AttributePosition, ClassPosition, Config, Severity};
+
+/// A class the visitor attached to an element, kept for the class-name manifest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratedClass {
+    pub component: String,
+    pub class_name: String,
+    /// The element's part of the class, e.g. `text-field`, which is also its key
+    /// in a CSS module.
+    pub component_class: String,
+    /// The opening element the class was first added to.
+    pub span: Span,
+}
+
+/// Adds a `className` derived from the file and element name to every JSX element.
+///
+/// Prefer [`crate::auto_classnames`] to compose the transform with other passes;
+/// use the visitor directly to read [`AddClassnameVisitor::generated`] afterwards.
 #[derive(Default)]
-pub struct AddClassnameVisitor<'a> {
-    filename: &'a str,
+pub struct AddClassnameVisitor {
+    filename: String,
+    config: Config,
+    generated: Vec<GeneratedClass>,
+    /// The default import of a `*.module.css` file, when `cssModules` is enabled.
+    css_module: Option<Ident>,
+    /// The file stem when it is listed in `ambiguousStems`, until the warning is emitted.
+    ambiguous_stem: Option<String>,
+    /// Every distinct element name decorated so far, as written, with its component
+    /// name and class.
+    sources: Vec<(String, String, String)>,
+    /// Own class parts of the decorated elements enclosing the current one.
+    parents: Vec<String>,
 }
 
-impl<'a> AddClassnameVisitor<'a> {
-    pub fn new(file_path: &'a str) -> Self {
-        let path = Path::new(file_path);
-        let filename: &str = path.file_stem().and_then(|stem| stem.to_str()).unwrap();
+impl AddClassnameVisitor {
+    /// Creates a visitor for the module at `file_path`. Only the file stem is used
+    /// for naming, and an empty path yields classes without a file part. The config
+    /// is used as is, see [`Config::validate`].
+    pub fn new(file_path: &str, config: Config) -> Self {
+        let file_path = config
+            .file_rules
+            .iter()
+            .fold(file_path.to_string(), |path, rule| {
+                rule.apply(&path).into_owned()
+            });
+        let stem = Path::new(&file_path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let alias = config.file_aliases.get(stem);
+        let filename = alias.map_or(stem, String::as_str).to_string();
+        let ambiguous_stem = (alias.is_none()
+            && config
+                .ambiguous_stems
+                .iter()
+                .any(|ambiguous| ambiguous == stem))
+        .then(|| stem.to_string());
+
+        AddClassnameVisitor {
+            filename,
+            config,
+            generated: Vec::new(),
+            css_module: None,
+            ambiguous_stem,
+            sources: Vec::new(),
+            parents: Vec::new(),
+        }
+    }
+
+    /// Every class added so far, at most one entry per component name.
+    pub fn generated(&self) -> &[GeneratedClass] {
+        &self.generated
+    }
+
+    /// Keeps every distinct class of an element: under `nestingDepth` the same
+    /// element gets a different class under each parent, and modifiers add more.
+    fn record(
+        &mut self,
+        component_name: &str,
+        class_name: &str,
+        component_class: &str,
+        span: Span,
+    ) {
+        if self.generated.iter().any(|generated| {
+            generated.component == component_name && generated.class_name == class_name
+        }) {
+            return;
+        }
+        self.generated.push(GeneratedClass {
+            component: component_name.to_string(),
+            class_name: class_name.to_string(),
+            component_class: component_class.to_string(),
+            span,
+        });
+    }
+
+    /// Warns when `source`, the element name as written, gets the same class as a
+    /// different element name did earlier in the file. Names mapped by
+    /// `componentAliases` collapse on purpose and are left alone, on either side.
+    fn check_collision(
+        &mut self,
+        source: &str,
+        component_name: &str,
+        class_name: &str,
+        span: Span,
+    ) {
+        if self
+            .sources
+            .iter()
+            .any(|(seen, _, seen_class)| seen == source && seen_class == class_name)
+        {
+            return;
+        }
+        let is_alias = |component: &str| self.config.component_aliases.contains_key(component);
+        let earlier = self
+            .sources
+            .iter()
+            .find(|(_, component, seen_class)| seen_class == class_name && !is_alias(component))
+            .filter(|_| !is_alias(component_name))
+            .map(|(seen, _, _)| seen.clone());
+        self.sources.push((
+            source.to_string(),
+            component_name.to_string(),
+            class_name.to_string(),
+        ));
+
+        if let Some(earlier) = earlier {
+            warn(
+                span,
+                &format!(
+                    "`{}` and `{}` both generate `{}`, so their styles can't be told apart",
+                    earlier, source, class_name
+                ),
+            );
+        }
+    }
+
+    /// Adds the generated class to a single opening element, merging it into a
+    /// literal `className` when there is one.
+    /// Returns the element's own part of the class when it was decorated, which
+    /// scopes its children under `nestingDepth`.
+    fn decorate(&mut self, n: &mut JSXOpeningElement) -> Option<String> {
+        let (component_name, source) = match &n.name {
+            JSXElementName::Ident(ident) => (ident.sym.to_string(), ident.sym.to_string()),
+            JSXElementName::JSXMemberExpr(expr) => (expr.prop.sym.to_string(), member_path(expr)),
+            JSXElementName::JSXNamespacedName(name) => {
+                let reason = format!("`{}:{}` has a namespaced name", name.ns.sym, name.name.sym);
+                self.report_undecorated(n.span, &reason);
+                return None;
+            }
+        };
+
+        if component_name.contains("Fragment") {
+            return None;
+        }
+
+        let component_class = self.component_class(&component_name);
+        let class_name: String = self.class_name(&component_class);
+        let modifiers: Vec<(String, String)> = self
+            .modifiers(n)
+            .into_iter()
+            .map(|modifier| {
+                let component_class = format!("{}{}", component_class, modifier);
+                (self.class_name(&component_class), component_class)
+            })
+            .collect();
+        let classes = std::iter::once(class_name.as_str())
+            .chain(modifiers.iter().map(|(class_name, _)| class_name.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        // Generated nodes borrow the opening element's span so source maps and
+        // diagnostics point back at the element they were added to.
+        let span = n.span;
+
+        let merged = match n.attrs.iter_mut().find_map(class_name_attr) {
+            // If you find the className attribute, append to it
+            Some(attr) => self.merge(attr, &classes),
+            None => {
+                let attr = JSXAttrOrSpread::JSXAttr(JSXAttr {
+                    span,
+                    name: JSXAttrName::Ident(Ident::new(js_word!("className"), span)),
+                    value: Some(JSXAttrValue::Lit(Lit::Str(Str {
+                        span,
+                        value: classes.as_str().into(),
+                        raw: None,
+                    }))),
+                });
+                let index = match self.config.attribute_position {
+                    AttributePosition::End => n.attrs.len(),
+                    AttributePosition::Start => 0,
+                    AttributePosition::BeforeSpread => n
+                        .attrs
+                        .iter()
+                        .position(|attr| matches!(attr, JSXAttrOrSpread::SpreadElement(_)))
+                        .unwrap_or(n.attrs.len()),
+                };
+                n.attrs.insert(index, attr);
+                Ok(())
+            }
+        };
+
+        if let Err(reason) = &merged {
+            self.report_undecorated(span, &format!("`{}` {}", source, reason));
+        } else {
+            self.record(&component_name, &class_name, &component_class, span);
+            for (class_name, component_class) in &modifiers {
+                self.record(&component_name, class_name, component_class, span);
+            }
+            self.check_collision(&source, &component_name, &class_name, span);
+            if let Some(stem) = self.ambiguous_stem.take() {
+                warn(
+                    span,
+                    &format!(
+                        "`{}` is listed in `ambiguousStems`, so `{}` may collide with \
+                         classes from another file; add a `fileAliases` entry for it",
+                        stem, class_name
+                    ),
+                );
+            }
+        }
+
+        if let Some(styles) = &self.css_module {
+            let key = self.component_class(&component_name);
+            if let Some(attr) = n.attrs.iter_mut().find_map(class_name_attr) {
+                add_css_module_class(attr, styles, &key);
+            }
+        }
+
+        merged
+            .ok()
+            .map(|()| self.camel_to_hyphen_case(&self.component_part(&component_name)))
+    }
+
+    /// BEM modifiers for the props listed in `modifiers`, to append to the element's
+    /// part of the class: `--primary` for `primary` or `primary={true}`, `--size-lg`
+    /// for `size="lg"`. Props with any other value are skipped.
+    fn modifiers(&self, n: &JSXOpeningElement) -> Vec<String> {
+        n.attrs
+            .iter()
+            .filter_map(|attr| match attr {
+                JSXAttrOrSpread::JSXAttr(JSXAttr {
+                    name: JSXAttrName::Ident(name),
+                    value,
+                    ..
+                }) if self.config.modifiers.iter().any(|prop| *prop == *name.sym) => {
+                    let value = match value {
+                        None => None,
+                        Some(JSXAttrValue::Lit(Lit::Str(value))) => Some(value.value.to_string()),
+                        Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                            expr: JSXExpr::Expr(expr),
+                            ..
+                        })) => match &**expr {
+                            Expr::Lit(Lit::Bool(value)) if value.value => None,
+                            expr => Some(static_string(expr)?),
+                        },
+                        _ => return None,
+                    };
+                    let modifier = match value {
+                        None => self.camel_to_hyphen_case(&name.sym),
+                        Some(value) if value.trim().is_empty() => return None,
+                        Some(value) => format!(
+                            "{}-{}",
+                            self.camel_to_hyphen_case(&name.sym),
+                            value.split_whitespace().collect::<Vec<_>>().join("-")
+                        ),
+                    };
+                    Some(format!("{}{}", self.config.modifier_separator, modifier))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Removes classes this file would generate from a literal `className`, dropping
+    /// the attribute when nothing is left. Used when `strip` is enabled. Returns the
+    /// element's own part of the class when it had the class, like `decorate`.
+    fn strip(&self, n: &mut JSXOpeningElement) -> Option<String> {
+        let component_name = match &n.name {
+            JSXElementName::Ident(ident) => ident.sym.to_string(),
+            JSXElementName::JSXMemberExpr(expr) => expr.prop.sym.to_string(),
+            JSXElementName::JSXNamespacedName(_) => return None,
+        };
+        let component_class = self.component_class(&component_name);
+        let class_name = self.class_name(&component_class);
+        let modifier_prefix = format!("{}{}", class_name, self.config.modifier_separator);
+        // Under `maxLength` modifier classes are hashed separately, so match the
+        // ones the element's props produce.
+        let modifiers: Vec<String> = self
+            .modifiers(n)
+            .into_iter()
+            .map(|modifier| self.class_name(&format!("{}{}", component_class, modifier)))
+            .collect();
+        // With a prefix, any class with this file's part was generated, even for a
+        // since-renamed element. Without one, that would also match handwritten
+        // classes like `button-group` in `Button.tsx`.
+        let file_prefix =
+            (!self.config.prefix.is_empty() && !self.filename.is_empty()).then(|| {
+                format!(
+                    "{}{}{}",
+                    self.config.prefix,
+                    self.camel_to_hyphen_case(&self.filename),
+                    self.config.separator
+                )
+            });
+        let is_generated = |token: &str| {
+            token == class_name
+                || token.starts_with(&modifier_prefix)
+                || modifiers.iter().any(|modifier| modifier == token)
+                || file_prefix
+                    .as_deref()
+                    .is_some_and(|file_prefix| token.starts_with(file_prefix))
+        };
+
+        let mut had_class = false;
+
+        n.attrs.retain_mut(|attr| {
+            let Some(attr) = class_name_attr(attr) else {
+                return true;
+            };
+            let value = match &mut attr.value {
+                Some(JSXAttrValue::Lit(Lit::Str(value))) => value,
+                Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                    expr: JSXExpr::Expr(expr),
+                    ..
+                })) => match &mut **expr {
+                    Expr::Lit(Lit::Str(value)) => value,
+                    _ => return true,
+                },
+                _ => return true,
+            };
+
+            let tokens: Vec<&str> = value.value.split_whitespace().collect();
+            had_class |= tokens.contains(&class_name.as_str());
+            let kept: Vec<&str> = tokens
+                .iter()
+                .copied()
+                .filter(|token| !is_generated(token))
+                .collect();
+            if kept.len() == tokens.len() {
+                return true;
+            }
+            if kept.is_empty() {
+                return false;
+            }
+            let kept = kept.join(" ");
+            value.raw = value.raw.as_deref().and_then(|raw| requote_raw(raw, &kept));
+            value.value = kept.into();
+            true
+        });
+
+        had_class.then(|| self.camel_to_hyphen_case(&self.component_part(&component_name)))
+    }
+
+    /// Reports an element left without a generated class at the `undecorated` severity.
+    fn report_undecorated(&self, span: Span, reason: &str) {
+        let message = format!("no class generated: {}", reason);
+        match self.config.undecorated {
+            Severity::Off => {}
+            Severity::Warn => warn(span, &message),
+            Severity::Error => error(span, &message),
+        }
+    }
+
+    /// Merges `class_name` into an existing `className` whose value is known at
+    /// compile time. Dynamic values are left alone and the reason is returned.
+    fn merge(&self, attr: &mut JSXAttr, class_name: &str) -> Result<(), &'static str> {
+        let position = self.config.class_position;
+
+        // A class list this plugin already built for a CSS module: merge into its
+        // static part and keep the lookup.
+        if let (
+            Some(styles),
+            Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                expr: JSXExpr::Expr(expr),
+                ..
+            })),
+        ) = (&self.css_module, &mut attr.value)
+        {
+            if let Some(existing) = css_module_classes(expr, styles) {
+                let existing_value =
+                    static_string(existing).ok_or("has a `className` computed at runtime")?;
+                let missing = missing_classes(&existing_value, class_name);
+                if !missing.is_empty() {
+                    **existing =
+                        Expr::Lit(Lit::Str(position.join(&existing_value, &missing).into()));
+                }
+                return Ok(());
+            }
+        }
+
+        let new_value = match &attr.value {
+            // Keep the author's quotes and escapes so the output diff stays minimal.
+            Some(JSXAttrValue::Lit(Lit::Str(existing_value))) => {
+                let missing = missing_classes(&existing_value.value, class_name);
+                if missing.is_empty() {
+                    return Ok(());
+                }
+                Str {
+                    span: existing_value.span,
+                    value: position.join(&existing_value.value, &missing).into(),
+                    raw: existing_value
+                        .raw
+                        .as_deref()
+                        .and_then(|raw| join_raw(raw, &missing, position)),
+                }
+            }
+            Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                span,
+                expr: JSXExpr::Expr(expr),
+            })) => {
+                let new_value = if is_nullish(expr) {
+                    class_name.to_string()
+                } else {
+                    let existing_value =
+                        static_string(expr).ok_or("has a `className` computed at runtime")?;
+                    let missing = missing_classes(&existing_value, class_name);
+                    if missing.is_empty() {
+                        return Ok(());
+                    }
+                    // JSX strings don't process escapes, so newlines and tabs from a
+                    // template would be printed as `\n` and merge their classes.
+                    let existing_value = existing_value.split_whitespace().collect::<Vec<_>>();
+                    position.join(&existing_value.join(" "), &missing)
+                };
+                let new_value = Str {
+                    span: *span,
+                    value: new_value.into(),
+                    raw: None,
+                };
+                // A backslash can't be written in a JSX string either.
+                if new_value.value.contains('\\') {
+                    attr.value = Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                        span: *span,
+                        expr: JSXExpr::Expr(Box::new(Expr::Lit(Lit::Str(new_value)))),
+                    }));
+                    return Ok(());
+                }
+                new_value
+            }
+            None => {
+                warn(
+                    attr.span,
+                    "`className` without a value renders as \"true\", \
+                     replaced it with the generated class",
+                );
+                Str {
+                    span: attr.span,
+                    value: class_name.into(),
+                    raw: None,
+                }
+            }
+            // JSX elements and fragments used as the value.
+            _ => return Err("has a `className` that is neither a string nor an expression"),
+        };
+
+        attr.value = Some(JSXAttrValue::Lit(Lit::Str(new_value)));
+        Ok(())
+    }
+
+    /// Builds `export const <name> = { Component: "class-name", ... };`, with an
+    /// array of classes for components that got more than one.
+    fn manifest_export(&self, export_name: &str) -> ModuleItem {
+        let props = classes_by_component(&self.generated)
+            .into_iter()
+            .map(|(component, classes)| {
+                // Intrinsic elements like `my-element` need a quoted key.
+                let key = if is_identifier(component) {
+                    PropName::Ident(Ident::new(component.into(), DUMMY_SP))
+                } else {
+                    PropName::Str(component.into())
+                };
+                let value = match classes.as_slice() {
+                    [class_name] => Expr::Lit(Lit::Str(Str::from(*class_name))),
+                    classes => Expr::Array(ArrayLit {
+                        span: DUMMY_SP,
+                        elems: classes
+                            .iter()
+                            .map(|class_name| {
+                                Some(ExprOrSpread {
+                                    spread: None,
+                                    expr: Box::new(Expr::Lit(Lit::Str(Str::from(*class_name)))),
+                                })
+                            })
+                            .collect(),
+                    }),
+                };
+                PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                    key,
+                    value: Box::new(value),
+                })))
+            })
+            .collect();
+
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+            span: DUMMY_SP,
+            decl: Decl::Var(Box::new(VarDecl {
+                span: DUMMY_SP,
+                kind: VarDeclKind::Const,
+                declare: false,
+                decls: vec![VarDeclarator {
+                    span: DUMMY_SP,
+                    name: Pat::Ident(BindingIdent::from(Ident::new(export_name.into(), DUMMY_SP))),
+                    init: Some(Box::new(Expr::Object(ObjectLit {
+                        span: DUMMY_SP,
+                        props,
+                    }))),
+                    definite: false,
+                }],
+            })),
+        }))
+    }
+
+    /// The full class for an element's part of the class, shortened to `maxLength`
+    /// only after any modifier was appended so it can't push the class over the limit.
+    fn class_name(&self, component: &str) -> String {
+        let body = if self.filename.is_empty() {
+            component.to_string()
+        } else {
+            format!(
+                "{}{}{}",
+                self.camel_to_hyphen_case(&self.filename),
+                self.config.separator,
+                component
+            )
+        };
+
+        match self.config.max_length {
+            Some(max_length) => truncate_with_hash(&self.config.prefix, &body, max_length),
+            None => format!("{}{}", self.config.prefix, body),
+        }
+    }
 
-        AddClassnameVisitor { filename }
+    /// The element's part of the class, e.g. `text-field` for `TextField`. Under
+    /// `nestingDepth` it is scoped to the nearest decorated ancestors, e.g.
+    /// `header__title`.
+    fn component_class(&self, component_name: &str) -> String {
+        let own = self.camel_to_hyphen_case(&self.component_part(component_name));
+        let Some(depth) = self.config.nesting_depth else {
+            return own;
+        };
+        let ancestors = &self.parents[self.parents.len().saturating_sub(depth)..];
+        ancestors
+            .iter()
+            .chain(std::iter::once(&own))
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(&self.config.element_separator)
     }
 
-    fn class_name(&self, component_name: &str) -> String {
-        format!(
-            "{}-{}",
-            self.camel_to_hyphen_case(self.filename),
-            self.camel_to_hyphen_case(component_name)
-        )
+    /// Applies `componentAliases`, or failing that `componentRules`, to an element name.
+    fn component_part(&self, component_name: &str) -> String {
+        if let Some(alias) = self.config.component_aliases.get(component_name) {
+            return alias.clone();
+        }
+        self.config
+            .component_rules
+            .iter()
+            .fold(component_name.to_string(), |name, rule| {
+                rule.apply(&name).into_owned()
+            })
     }
 
     fn camel_to_hyphen_case(&self, camel_case: &str) -> String {
@@ -50,97 +595,378 @@ impl<'a> AddClassnameVisitor<'a> {
     }
 }
 
-impl<'a> VisitMut for AddClassnameVisitor<'a> {
-    /**
-     * The VisitMut trait is used to traverse the AST and modify it in place.
-     * visit_mut_jsx_opening_element is called when the visitor encounters a tag in the JSX.
-     * We add the className attribute to the React node for it to be converted to a CSS class.
-     */
-    fn visit_mut_jsx_opening_element(&mut self, n: &mut JSXOpeningElement) {
-        let component_name = match &n.name {
-            JSXElementName::Ident(ident) => ident.sym.to_string(),
-            JSXElementName::JSXMemberExpr(expr) => match &expr.prop {
-                Ident { sym, .. } => sym.to_string(),
-            },
-            _ => return,
-        };
+fn class_name_attr(attr: &mut JSXAttrOrSpread) -> Option<&mut JSXAttr> {
+    match attr {
+        JSXAttrOrSpread::JSXAttr(attr) => match &attr.name {
+            JSXAttrName::Ident(ident) if ident.sym == js_word!("className") => Some(attr),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
-        if component_name.contains("Fragment") {
+const CSS_MODULE_EXTENSIONS: &[&str] = &[
+    ".module.css",
+    ".module.scss",
+    ".module.sass",
+    ".module.less",
+];
+
+/// The local name of `import styles from "./Button.module.css"` or
+/// `import * as styles from "./Button.module.css"`.
+fn css_module_import(item: &ModuleItem) -> Option<Ident> {
+    let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item else {
+        return None;
+    };
+    if import.type_only
+        || !CSS_MODULE_EXTENSIONS
+            .iter()
+            .any(|extension| import.src.value.ends_with(extension))
+    {
+        return None;
+    }
+    import
+        .specifiers
+        .iter()
+        .find_map(|specifier| match specifier {
+            ImportSpecifier::Default(default) => Some(default.local.clone()),
+            ImportSpecifier::Namespace(namespace) => Some(namespace.local.clone()),
+            ImportSpecifier::Named(_) => None,
+        })
+}
+
+/// Rewrites `className` to `{[existing, styles["key"]].join(" ")}`. Missing keys
+/// are `undefined`, which `join` turns into an empty string.
+fn add_css_module_class(attr: &mut JSXAttr, styles: &Ident, key: &str) {
+    if let Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+        expr: JSXExpr::Expr(expr),
+        ..
+    })) = &mut attr.value
+    {
+        if css_module_classes(expr, styles).is_some() {
             return;
         }
+    }
 
-        let class_name: String = self.class_name(&component_name);
-
-        let has_class_name = n.attrs.iter_mut().any(|attr| match attr {
-            JSXAttrOrSpread::JSXAttr(JSXAttr { name, value, .. }) => {
-                if let JSXAttrName::Ident(ident) = name {
-                    // If you find the className attribute, append to it
-                    if ident.sym == js_word!("className") {
-                        if let Some(JSXAttrValue::Lit(Lit::Str(existing_value))) = value {
-                            let new_value = Lit::Str(Str {
-                                span: DUMMY_SP,
-                                value: format!("{} {}", existing_value.value, class_name).into(),
-                                raw: None,
-                            });
-                            *value = Some(JSXAttrValue::Lit(new_value));
-                        }
-                        return true;
-                    }
+    let span = attr.span;
+    let existing = match attr.value.take() {
+        Some(JSXAttrValue::Lit(lit)) => Box::new(Expr::Lit(lit)),
+        Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+            expr: JSXExpr::Expr(expr),
+            ..
+        })) => expr,
+        value => {
+            attr.value = value;
+            return;
+        }
+    };
 
-                    // If the value of a prop is a function that returns JSX or a JSXComponent
-                    // we need to visit it to add the className attribute
-                    if let Some(JSXAttrValue::JSXExprContainer(expr_container)) = value {
-                        self.visit_mut_jsx_expr_container(expr_container);
-                    }
-                }
-                false
-            }
-            _ => false,
-        });
+    let lookup = Expr::Member(MemberExpr {
+        span,
+        obj: Box::new(Expr::Ident(styles.clone())),
+        prop: MemberProp::Computed(ComputedPropName {
+            span,
+            expr: Box::new(Expr::Lit(Lit::Str(key.into()))),
+        }),
+    });
+    let classes = Expr::Array(ArrayLit {
+        span,
+        elems: vec![
+            Some(ExprOrSpread {
+                spread: None,
+                expr: existing,
+            }),
+            Some(ExprOrSpread {
+                spread: None,
+                expr: Box::new(lookup),
+            }),
+        ],
+    });
+    let joined = Expr::Call(CallExpr {
+        span,
+        callee: Callee::Expr(Box::new(Expr::Member(MemberExpr {
+            span,
+            obj: Box::new(classes),
+            prop: MemberProp::Ident(Ident::new("join".into(), span)),
+        }))),
+        args: vec![ExprOrSpread {
+            spread: None,
+            expr: Box::new(Expr::Lit(Lit::Str(" ".into()))),
+        }],
+        type_args: None,
+    });
 
-        if !has_class_name {
-            n.attrs.push(JSXAttrOrSpread::JSXAttr(JSXAttr {
-                span: DUMMY_SP,
-                name: JSXAttrName::Ident(Ident::new(js_word!("className"), DUMMY_SP)),
-                value: Some(JSXAttrValue::Lit(Lit::Str(Str {
-                    span: DUMMY_SP,
-                    value: class_name.into(),
-                    raw: None,
-                }))),
-            }));
-        }
-    }
-
-    fn visit_mut_jsx_expr_container(&mut self, expr_container: &mut JSXExprContainer) {
-        match &mut expr_container.expr {
-            JSXExpr::Expr(expr) => {
-                if let Expr::Arrow(arrow_expr) = &mut **expr {
-                    match &mut *arrow_expr.body {
-                        BlockStmtOrExpr::Expr(inner_expr) => {
-                            // Adjusted handling for boxed expressions.
-                            // Dereference the boxed expression to inspect it.
-                            if let Expr::JSXElement(element) = &mut **inner_expr {
-                                element.visit_mut_with(self);
-                            }
-                        }
-                        BlockStmtOrExpr::BlockStmt(block_stmt) => {
-                            // Iterate over statements in block statement for return statements.
-                            for stmt in &mut block_stmt.stmts {
-                                if let Stmt::Return(return_stmt) = stmt {
-                                    if let Some(returned_expr) = &mut return_stmt.arg {
-                                        // Again, properly dereference the boxed expression to inspect it.
-                                        if let Expr::JSXElement(element) = &mut **returned_expr {
-                                            element.visit_mut_with(self);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+    attr.value = Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+        span,
+        expr: JSXExpr::Expr(Box::new(joined)),
+    }));
+}
+
+/// The first element of `[classes, styles["key"]].join(" ")`, as built by
+/// `add_css_module_class`.
+fn css_module_classes<'a>(expr: &'a mut Expr, styles: &Ident) -> Option<&'a mut Box<Expr>> {
+    let Expr::Call(CallExpr {
+        callee: Callee::Expr(callee),
+        args,
+        ..
+    }) = expr
+    else {
+        return None;
+    };
+    let Expr::Member(MemberExpr {
+        obj,
+        prop: MemberProp::Ident(method),
+        ..
+    }) = &mut **callee
+    else {
+        return None;
+    };
+    let Expr::Array(ArrayLit { elems, .. }) = &mut **obj else {
+        return None;
+    };
+    let is_lookup = |expr: &Expr| match expr {
+        Expr::Member(MemberExpr {
+            obj,
+            prop: MemberProp::Computed(_),
+            ..
+        }) => matches!(&**obj, Expr::Ident(ident) if ident.sym == styles.sym),
+        _ => false,
+    };
+    match elems.as_mut_slice() {
+        [Some(ExprOrSpread {
+            spread: None,
+            expr: existing,
+        }), Some(ExprOrSpread {
+            spread: None,
+            expr: lookup,
+        })] if method.sym == *"join" && args.len() == 1 && is_lookup(lookup) => Some(existing),
+        _ => None,
+    }
+}
+
+/// The classes of `class_name` that `existing` doesn't have yet, so merging into
+/// an already decorated `className` leaves it as is.
+fn missing_classes(existing: &str, class_name: &str) -> String {
+    class_name
+        .split_whitespace()
+        .filter(|class| !existing.split_whitespace().any(|token| token == *class))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Adds `class_name` inside the quotes of a raw string literal, keeping the
+/// original quote character and escape sequences.
+fn join_raw(raw: &str, class_name: &str, position: ClassPosition) -> Option<Atom> {
+    let quote = raw
+        .chars()
+        .next()
+        .filter(|quote| *quote == '"' || *quote == '\'')?;
+    let body = raw.strip_prefix(quote)?.strip_suffix(quote)?;
+    Some(format!("{quote}{}{quote}", position.join(body, class_name)).into())
+}
+
+/// Writes `value` in the quote character of the raw string literal it replaces.
+/// `None` when `value` would need escaping, leaving the quotes to the printer.
+fn requote_raw(raw: &str, value: &str) -> Option<Atom> {
+    let quote = raw
+        .chars()
+        .next()
+        .filter(|quote| *quote == '"' || *quote == '\'')?;
+    if value.contains(quote) || value.contains('\\') {
+        return None;
+    }
+    Some(format!("{quote}{value}{quote}").into())
+}
+
+/// Whether a `className` expression is `null` or `undefined`, which React renders
+/// as no class at all.
+fn is_nullish(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lit(Lit::Null(_)) => true,
+        Expr::Ident(ident) => ident.sym == js_word!("undefined"),
+        Expr::Paren(paren) => is_nullish(&paren.expr),
+        _ => false,
+    }
+}
+
+fn warn(span: Span, message: &str) {
+    if HANDLER.is_set() {
+        HANDLER.with(|handler| handler.struct_span_warn(span, message).emit());
+    }
+}
+
+fn error(span: Span, message: &str) {
+    if HANDLER.is_set() {
+        HANDLER.with(|handler| handler.struct_span_err(span, message).emit());
+    }
+}
+
+/// The classes generated for each component, both in source order.
+pub(crate) fn classes_by_component(generated: &[GeneratedClass]) -> Vec<(&str, Vec<&str>)> {
+    let mut components: Vec<(&str, Vec<&str>)> = vec![];
+    for generated in generated {
+        let class_name = generated.class_name.as_str();
+        match components
+            .iter_mut()
+            .find(|(component, _)| *component == generated.component)
+        {
+            Some((_, classes)) => classes.push(class_name),
+            None => components.push((&generated.component, vec![class_name])),
+        }
+    }
+    components
+}
+
+/// Whether `item` is `export const <name> = ...`.
+pub(crate) fn exports_const(item: &ModuleItem, name: &str) -> bool {
+    let ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+        decl: Decl::Var(var),
+        ..
+    })) = item
+    else {
+        return false;
+    };
+    var.kind == VarDeclKind::Const
+        && var
+            .decls
+            .iter()
+            .any(|decl| matches!(&decl.name, Pat::Ident(ident) if ident.id.sym == *name))
+}
+
+/// Whether `name` can be written as a bare object key.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// A member element name as written, e.g. `Layout.Panel`.
+fn member_path(expr: &JSXMemberExpr) -> String {
+    let object = match &expr.obj {
+        JSXObject::Ident(ident) => ident.sym.to_string(),
+        JSXObject::JSXMemberExpr(inner) => member_path(inner),
+    };
+    format!("{}.{}", object, expr.prop.sym)
+}
+
+/// The value of an expression that always evaluates to the same string: a string
+/// literal, a template without substitutions, or a concatenation of those.
+fn static_string(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(Lit::Str(value)) => Some(value.value.to_string()),
+        Expr::Tpl(tpl) if tpl.exprs.is_empty() => tpl
+            .quasis
+            .iter()
+            .map(|quasi| quasi.cooked.as_ref().map(|cooked| cooked.to_string()))
+            .collect(),
+        Expr::Bin(BinExpr {
+            op: BinaryOp::Add,
+            left,
+            right,
+            ..
+        }) => Some(format!("{}{}", static_string(left)?, static_string(right)?)),
+        Expr::Paren(paren) => static_string(&paren.expr),
+        _ => None,
+    }
+}
+
+pub(crate) const HASH_LENGTH: usize = 6;
+
+/// Shortens `prefix` + `body` to `max_length` characters, replacing the tail of
+/// `body` with a hash of the full name so that truncated names stay distinct.
+/// The prefix is always kept, and so is the first character of `body` so the
+/// class never starts with a digit of the hash.
+fn truncate_with_hash(prefix: &str, body: &str, max_length: usize) -> String {
+    let class_name = format!("{}{}", prefix, body);
+    if class_name.chars().count() <= max_length {
+        return class_name;
+    }
+
+    let hash = stable_hash(&class_name);
+    let keep = max_length.saturating_sub(prefix.chars().count() + HASH_LENGTH + 1);
+    let head: String = body.chars().take(keep).collect();
+    let head = head.trim_end_matches('-');
+    if head.is_empty() {
+        let first: String = body.chars().take(1).collect();
+        return format!("{}{}{}", prefix, first, hash);
+    }
+    format!("{}{}-{}", prefix, head, hash)
+}
+
+/// FNV-1a, printed in base 36. Unlike `DefaultHasher` its output is fixed across
+/// Rust versions and platforms, so class names don't change between builds.
+fn stable_hash(value: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in value.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    (0..HASH_LENGTH)
+        .map(|_| {
+            let digit = DIGITS[(hash % 36) as usize] as char;
+            hash /= 36;
+            digit
+        })
+        .collect()
+}
+
+impl VisitMut for AddClassnameVisitor {
+    fn visit_mut_module(&mut self, module: &mut Module) {
+        if self.config.css_modules {
+            self.css_module = module.body.iter().find_map(css_module_import);
+        }
+
+        module.visit_mut_children_with(self);
+
+        let export_name = self
+            .config
+            .manifest_export
+            .as_ref()
+            .and_then(|manifest| manifest.name());
+        if let Some(export_name) = export_name {
+            if !self.generated.is_empty() {
+                let manifest = self.manifest_export(export_name);
+                // Replace the manifest of source that already has the classes inlined.
+                match module
+                    .body
+                    .iter_mut()
+                    .find(|item| exports_const(item, export_name))
+                {
+                    Some(item) => *item = manifest,
+                    None => module.body.push(manifest),
                 }
             }
-            _ => {}
         }
-        expr_container.visit_mut_children_with(self);
+    }
+
+    /**
+     * The VisitMut trait is used to traverse the AST and modify it in place.
+     * visit_mut_jsx_element is called when the visitor encounters a tag in the JSX.
+     * We add the className attribute to the React node for it to be converted to a CSS class.
+     *
+     * Each element is reached exactly once by the default traversal: children below,
+     * and JSX nested in attributes (element values, render props, function expressions,
+     * spreads) by visiting the opening element's attributes. A decorated element is kept
+     * on the parent stack while its children are visited, for `nestingDepth`.
+     */
+    fn visit_mut_jsx_element(&mut self, n: &mut JSXElement) {
+        let own_class = if self.config.strip {
+            self.strip(&mut n.opening)
+        } else {
+            self.decorate(&mut n.opening)
+        };
+        n.opening.visit_mut_children_with(self);
+
+        let pushed = own_class.is_some();
+        self.parents.extend(own_class);
+        n.children.visit_mut_with(self);
+        n.closing.visit_mut_with(self);
+        if pushed {
+            self.parents.pop();
+        }
     }
 }
@@ -0,0 +1,173 @@
+//! `auto-classnames collisions`: finds classes generated by more than one file,
+//! such as `table-row` from both `orders/Table.tsx` and `users/Table.tsx`.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use serde_json::{json, Value};
+use swc_plugin_react_auto_classnames::Config;
+
+use crate::{current_dir, read_config, source_files, transform, USAGE};
+
+struct Options {
+    report: bool,
+    config: Config,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        report: false,
+        config: Config::default(),
+        paths: vec![],
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--report" => match args.next().as_deref() {
+                Some("json") => options.report = true,
+                Some(format) => return Err(format!("unsupported report format `{}`", format)),
+                None => return Err("--report expects a format".into()),
+            },
+            "--config" => options.config = read_config(args.next())?,
+            flag if flag.starts_with("--") => return Err(format!("unknown option `{}`", flag)),
+            path => options.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err("no paths given".into());
+    }
+    Ok(options)
+}
+
+/// Where a class was first generated in a file.
+#[derive(Debug, PartialEq)]
+struct Source {
+    file: String,
+    line: usize,
+    column: usize,
+}
+
+/// Maps every class generated by more than one file to its first element in
+/// each of those files, in order.
+fn collisions(generated: &[(String, Source)]) -> BTreeMap<&str, Vec<&Source>> {
+    let mut sources: BTreeMap<&str, Vec<&Source>> = BTreeMap::new();
+    for (class_name, source) in generated {
+        let files = sources.entry(class_name).or_default();
+        if files.iter().all(|seen| seen.file != source.file) {
+            files.push(source);
+        }
+    }
+    sources.retain(|_, files| files.len() > 1);
+    sources
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> ExitCode {
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let files = match source_files(&options.paths) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let cwd = current_dir();
+
+    let mut failed = false;
+    let mut generated = vec![];
+    for file in files {
+        match transform(&file, &options.config, &cwd) {
+            Ok(Some(output)) => {
+                for class in &output.generated {
+                    let (line, column) = output.position(class.span);
+                    let source = Source {
+                        file: file.to_string_lossy().into_owned(),
+                        line,
+                        column,
+                    };
+                    generated.push((class.class_name.clone(), source));
+                }
+            }
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("error: {}", err);
+                failed = true;
+            }
+        }
+    }
+
+    let collisions = collisions(&generated);
+    if options.report {
+        let records = collisions
+            .iter()
+            .map(|(class_name, sources)| {
+                let sources: Vec<Value> = sources
+                    .iter()
+                    .map(|source| {
+                        json!({ "file": source.file, "line": source.line, "column": source.column })
+                    })
+                    .collect();
+                json!({ "class": class_name, "sources": sources })
+            })
+            .collect();
+        println!("{}", Value::Array(records));
+    } else {
+        for (class_name, sources) in &collisions {
+            let sources: Vec<String> = sources
+                .iter()
+                .map(|source| format!("{}:{}:{}", source.file, source.line, source.column))
+                .collect();
+            println!(".{}: {}", class_name, sources.join(", "));
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if !collisions.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{collisions, Source};
+
+    fn generated(file: &str, line: usize, class_name: &str) -> (String, Source) {
+        let source = Source {
+            file: file.into(),
+            line,
+            column: 5,
+        };
+        (class_name.into(), source)
+    }
+
+    #[test]
+    fn reports_classes_shared_between_files() {
+        let generated = vec![
+            generated("orders/Table.tsx", 3, "table-row"),
+            generated("orders/Table.tsx", 4, "table-cell"),
+            generated("users/Table.tsx", 7, "table-avatar"),
+            generated("users/Table.tsx", 8, "table-row"),
+            generated("Layout.tsx", 2, "layout-row"),
+            generated("Layout.tsx", 9, "layout-row"),
+        ];
+
+        let collisions = collisions(&generated);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(
+            collisions["table-row"],
+            vec![&generated[0].1, &generated[3].1]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! `auto-classnames eject`: writes the generated classes into the source files
+//! themselves, keeping their formatting, so the plugin can be dropped.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use swc_plugin_react_auto_classnames::transform::eject_file;
+use swc_plugin_react_auto_classnames::Config;
+
+use crate::{current_dir, process, read_config, source_files, USAGE};
+
+struct Options {
+    config: Config,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        config: Config::default(),
+        paths: vec![],
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => options.config = read_config(args.next())?,
+            flag if flag.starts_with("--") => return Err(format!("unknown option `{}`", flag)),
+            path => options.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err("no paths given".into());
+    }
+    Ok(options)
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> ExitCode {
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let files = match source_files(&options.paths) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let cwd = current_dir();
+
+    let mut failed = false;
+    for file in files {
+        let output = match process(&file, &options.config, &cwd, eject_file) {
+            Ok(Some(output)) if output.changed => output,
+            Ok(_) => continue,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                failed = true;
+                continue;
+            }
+        };
+
+        match fs::write(&file, &output.code) {
+            Ok(()) => println!("wrote {}", file.display()),
+            Err(err) => {
+                eprintln!("error: {}: {}", file.display(), err);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,287 @@
+//! Runs the auto-classnames transform over a source tree without going through
+//! SWC or Node, to preview its effect or to use it as a pre-commit check.
+
+mod collisions;
+mod eject;
+mod stale;
+mod stubs;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use serde_json::{json, Value};
+use swc_plugin_react_auto_classnames::transform::{classes_json, transform_file, TransformOutput};
+use swc_plugin_react_auto_classnames::Config;
+
+const USAGE: &str = "\
+Usage: auto-classnames [--check | --write | --print] [--report json] [--config <file>] <path>...
+       auto-classnames stubs [--ext <extension>] [--config <file>] <path>...
+       auto-classnames collisions [--report json] [--config <file>] <path>...
+       auto-classnames eject [--config <file>] <path>...
+       auto-classnames stale [--pattern <regex>] [--report json] [--config <file>] <path>...
+
+Transforms every .tsx and .jsx file found under the given paths.
+
+Options:
+  --check          Exit with status 1 if any file would be changed
+  --write          Rewrite changed files in place
+  --print          Print the transformed source of every file
+  --report json    Print the generated classes of every file as JSON (not with --print)
+  --config <file>  Read plugin options from a JSON file
+
+Commands:
+  stubs            Add empty rules for every generated class to a stylesheet next to
+                   each component file, keeping existing rules
+    --ext <ext>    Stylesheet extension: css (default), scss, module.css or module.scss
+  collisions       List classes generated by more than one file, exiting with status 1
+                   if there are any
+  eject            Write the generated classes into the source files, editing only the
+                   touched attributes, so the plugin can be removed
+  stale            List class selectors in .css and .scss files that no source file
+                   generates anymore, exiting with status 1 if there are any
+    --pattern <re> Only consider classes matching this regex; defaults to classes
+                   starting with the configured prefix";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Check,
+    Write,
+    Print,
+}
+
+#[derive(Default)]
+struct Options {
+    mode: Option<Mode>,
+    report: bool,
+    config: Config,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        let mode = match arg.as_str() {
+            "--check" => Mode::Check,
+            "--write" => Mode::Write,
+            "--print" => Mode::Print,
+            "--report" => match args.next().as_deref() {
+                Some("json") => {
+                    options.report = true;
+                    continue;
+                }
+                Some(format) => return Err(format!("unsupported report format `{}`", format)),
+                None => return Err("--report expects a format".into()),
+            },
+            "--config" => {
+                options.config = read_config(args.next())?;
+                continue;
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option `{}`", flag)),
+            path => {
+                options.paths.push(PathBuf::from(path));
+                continue;
+            }
+        };
+
+        if options.mode.replace(mode).is_some() {
+            return Err("only one of --check, --write and --print may be given".into());
+        }
+    }
+
+    if options.mode.is_none() && !options.report {
+        return Err("nothing to do".into());
+    }
+    // Both print to stdout, which would make the report unparsable.
+    if options.report && matches!(options.mode, Some(Mode::Print)) {
+        return Err("--print cannot be combined with --report".into());
+    }
+    if options.paths.is_empty() {
+        return Err("no paths given".into());
+    }
+    Ok(options)
+}
+
+/// Reads plugin options from the JSON file following `--config`.
+fn read_config(path: Option<String>) -> Result<Config, String> {
+    let path = path.ok_or("--config expects a file")?;
+    let json = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))?;
+    serde_json::from_str(&json).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Collects files ending in one of `extensions` under `path`, skipping
+/// `node_modules` and hidden directories.
+fn collect_files(path: &Path, extensions: &[&str], files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let name = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if entry.is_dir() {
+            if name != "node_modules" && !name.starts_with('.') {
+                collect_files(&entry, extensions, files)?;
+            }
+        } else if extensions.iter().any(|extension| name.ends_with(extension)) {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Every file under `paths` ending in one of `extensions`, in a stable order.
+fn find_files(paths: &[PathBuf], extensions: &[&str]) -> Result<Vec<PathBuf>, String> {
+    let mut files = vec![];
+    for path in paths {
+        collect_files(path, extensions, &mut files)
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    Ok(files)
+}
+
+/// Every `.tsx` and `.jsx` file under `paths`, in a stable order.
+fn source_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    find_files(paths, &[".tsx", ".jsx"])
+}
+
+fn current_dir() -> String {
+    std::env::current_dir()
+        .map(|cwd| cwd.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Resolves the config for `file` and transforms it, or returns `None` when the
+/// config excludes the file.
+fn transform(file: &Path, config: &Config, cwd: &str) -> Result<Option<TransformOutput>, String> {
+    process(file, config, cwd, transform_file)
+}
+
+/// Resolves the config for `file` and runs `process_file` on its source, printing
+/// any warnings. Returns `None` when the config excludes the file.
+fn process(
+    file: &Path,
+    config: &Config,
+    cwd: &str,
+    process_file: fn(&str, &str, &Config) -> Result<TransformOutput, String>,
+) -> Result<Option<TransformOutput>, String> {
+    let file_path = file.to_string_lossy();
+    let config = config
+        .resolve(&file_path, cwd)
+        .map_err(|err| format!("{}: {}", file_path, err))?;
+    if !config
+        .applies_to(&file_path, cwd)
+        .map_err(|err| format!("{}: {}", file_path, err))?
+    {
+        return Ok(None);
+    }
+
+    let source = fs::read_to_string(file).map_err(|err| format!("{}: {}", file_path, err))?;
+    let output = process_file(&file_path, &source, &config)?;
+    for diagnostic in &output.diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    Ok(Some(output))
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("stubs") => return stubs::run(args.skip(1)),
+        Some("collisions") => return collisions::run(args.skip(1)),
+        Some("eject") => return eject::run(args.skip(1)),
+        Some("stale") => return stale::run(args.skip(1)),
+        _ => {}
+    }
+
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let files = match source_files(&options.paths) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let cwd = current_dir();
+
+    let mut failed = false;
+    let mut would_change = false;
+    let mut records = vec![];
+
+    for file in files {
+        let file_path = file.to_string_lossy();
+        let output = match transform(&file, &options.config, &cwd) {
+            Ok(Some(output)) => output,
+            Ok(None) => continue,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                failed = true;
+                continue;
+            }
+        };
+
+        match options.mode {
+            Some(Mode::Check) if output.changed => {
+                // Keep stdout for the report.
+                if options.report {
+                    eprintln!("{}", file_path);
+                } else {
+                    println!("{}", file_path);
+                }
+                would_change = true;
+            }
+            Some(Mode::Write) if output.changed => {
+                if let Err(err) = fs::write(&file, &output.code) {
+                    eprintln!("error: {}: {}", file_path, err);
+                    failed = true;
+                    continue;
+                }
+                if options.report {
+                    eprintln!("wrote {}", file_path);
+                } else {
+                    println!("wrote {}", file_path);
+                }
+            }
+            Some(Mode::Print) => println!("// {}\n{}", file_path, output.code),
+            _ => {}
+        }
+
+        if options.report {
+            let classes = classes_json(&output.generated);
+            records.push(json!({
+                "file": file_path,
+                "changed": output.changed,
+                "classes": classes,
+            }));
+        }
+    }
+
+    if options.report {
+        println!("{}", Value::Array(records));
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if would_change {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
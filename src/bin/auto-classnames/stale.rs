@@ -0,0 +1,279 @@
+//! `auto-classnames stale`: finds class selectors in stylesheets that look like
+//! generated classes but that no current source file generates, typically left
+//! behind after a component or file was renamed.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use regex::Regex;
+use serde_json::{json, Value};
+use swc_plugin_react_auto_classnames::Config;
+
+use crate::{current_dir, find_files, read_config, source_files, transform, USAGE};
+
+const STYLESHEET_EXTENSIONS: &[&str] = &[".css", ".scss"];
+
+struct Options {
+    pattern: Option<Regex>,
+    report: bool,
+    config: Config,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        pattern: None,
+        report: false,
+        config: Config::default(),
+        paths: vec![],
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pattern" => {
+                let pattern = args.next().ok_or("--pattern expects a regex")?;
+                let pattern = Regex::new(&pattern).map_err(|err| err.to_string())?;
+                options.pattern = Some(pattern);
+            }
+            "--report" => match args.next().as_deref() {
+                Some("json") => options.report = true,
+                Some(format) => return Err(format!("unsupported report format `{}`", format)),
+                None => return Err("--report expects a format".into()),
+            },
+            "--config" => options.config = read_config(args.next())?,
+            flag if flag.starts_with("--") => return Err(format!("unknown option `{}`", flag)),
+            path => options.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if options.pattern.is_none() {
+        if options.config.prefix.is_empty() {
+            return Err(
+                "no `prefix` configured, pass --pattern to select generated classes".into(),
+            );
+        }
+        let pattern = format!("^{}", regex::escape(&options.config.prefix));
+        options.pattern = Some(Regex::new(&pattern).expect("escaped prefix is a valid regex"));
+    }
+    if options.paths.is_empty() {
+        return Err("no paths given".into());
+    }
+    Ok(options)
+}
+
+/// Blanks out comments, strings, `url(...)` values and SCSS interpolations in
+/// `css`, keeping newlines so byte offsets and line numbers still match the
+/// original. `//` starts a comment only in SCSS.
+fn strip_non_selectors(css: &str, scss: bool) -> String {
+    let blank = |text: &str| -> String {
+        text.chars()
+            .map(|c| if c == '\n' { '\n' } else { ' ' })
+            .collect()
+    };
+
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(c) = rest.chars().next() {
+        let end = if rest.starts_with("/*") {
+            rest.find("*/").map_or(rest.len(), |end| end + 2)
+        } else if rest.starts_with("url(") {
+            // Unquoted URLs may contain `//`, quotes or dots.
+            rest.find(')').map_or(rest.len(), |end| end + 1)
+        } else if scss && rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("#{") {
+            rest.find('}').map_or(rest.len(), |end| end + 1)
+        } else if c == '"' || c == '\'' {
+            rest[1..].find(c).map_or(rest.len(), |end| end + 2)
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        out.push_str(&blank(&rest[..end]));
+        rest = &rest[end..];
+    }
+    out
+}
+
+/// Every class used in a selector of `css`, with the line it first appears on.
+fn selector_classes(css: &str, scss: bool) -> Vec<(usize, String)> {
+    let css = strip_non_selectors(css, scss);
+    let mut classes: Vec<(usize, String)> = vec![];
+    let mut start = 0;
+
+    for (index, c) in css.char_indices() {
+        match c {
+            '{' => {
+                let prelude = &css[start..index];
+                // At-rule preludes like `@media (...)` are not selectors.
+                if !prelude.trim_start().starts_with('@') {
+                    for (offset, class) in prelude_classes(prelude) {
+                        if classes.iter().all(|(_, seen)| *seen != class) {
+                            let line = css[..start + offset].matches('\n').count() + 1;
+                            classes.push((line, class));
+                        }
+                    }
+                }
+                start = index + 1;
+            }
+            '}' | ';' => start = index + 1,
+            _ => {}
+        }
+    }
+    classes
+}
+
+/// The `.class` names in a selector list, with their byte offsets.
+fn prelude_classes(prelude: &str) -> Vec<(usize, String)> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    prelude
+        .match_indices('.')
+        .filter_map(|(index, _)| {
+            let name = &prelude[index + 1..];
+            let end = name.find(|c| !is_name_char(c)).unwrap_or(name.len());
+            let name = &name[..end];
+            name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '-' || c == '_')
+                .then(|| (index, name.to_string()))
+        })
+        .collect()
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> ExitCode {
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let pattern = options.pattern.expect("pattern is set by parse_args");
+
+    let files = source_files(&options.paths)
+        .and_then(|sources| Ok((sources, find_files(&options.paths, STYLESHEET_EXTENSIONS)?)));
+    let (sources, stylesheets) = match files {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let cwd = current_dir();
+
+    let mut failed = false;
+    let mut generated = BTreeSet::new();
+    for file in sources {
+        match transform(&file, &options.config, &cwd) {
+            Ok(Some(output)) => {
+                generated.extend(output.generated.into_iter().map(|g| g.class_name))
+            }
+            Ok(None) => {}
+            Err(err) => {
+                eprintln!("error: {}", err);
+                failed = true;
+            }
+        }
+    }
+    // Without every source file's classes, anything could look stale.
+    if failed {
+        return ExitCode::from(2);
+    }
+
+    let mut records = vec![];
+    for stylesheet in stylesheets {
+        let file_path = stylesheet.to_string_lossy();
+        // CSS module keys are scoped to their file and never carry the file part.
+        if file_path.ends_with(".module.css") || file_path.ends_with(".module.scss") {
+            continue;
+        }
+        let css = match fs::read_to_string(&stylesheet) {
+            Ok(css) => css,
+            Err(err) => {
+                eprintln!("error: {}: {}", file_path, err);
+                failed = true;
+                continue;
+            }
+        };
+
+        for (line, class) in selector_classes(&css, file_path.ends_with(".scss")) {
+            if !pattern.is_match(&class) || generated.contains(&class) {
+                continue;
+            }
+            if !options.report {
+                println!("{}:{}: .{}", file_path, line, class);
+            }
+            records.push(json!({ "file": file_path, "line": line, "class": class }));
+        }
+    }
+
+    let stale = !records.is_empty();
+    if options.report {
+        println!("{}", Value::Array(records));
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if stale {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::selector_classes;
+
+    #[test]
+    fn finds_classes_in_selectors_only() {
+        let css = "\
+/* .commented-out { } */
+.card-row, .card-row:hover > .card-icon {
+  background: url(\"img/bg.png\") 0.5em;
+}
+@media (min-width: 10em) {
+  .card-row { }
+}
+";
+        assert_eq!(
+            selector_classes(css, false),
+            vec![(2, "card-row".into()), (2, "card-icon".into())]
+        );
+    }
+
+    #[test]
+    fn double_slashes_are_not_comments_in_css() {
+        let css = ".a{background:url(//cdn.x/a.png)}.acme-old{color:red}// .b{}";
+        assert_eq!(
+            selector_classes(css, false),
+            vec![(1, "a".into()), (1, "acme-old".into()), (1, "b".into())]
+        );
+        assert_eq!(
+            selector_classes(".a{background:url(//cdn.x/a.png)}.acme-old{}", true),
+            vec![(1, "a".into()), (1, "acme-old".into())]
+        );
+    }
+
+    #[test]
+    fn handles_scss_nesting_and_interpolation() {
+        let scss = "\
+// .old-row { }
+.card {
+  &.card-active { color: red; }
+  #{$parent}.card-title { }
+  a[href='x.y'] .card-link { }
+}
+";
+        assert_eq!(
+            selector_classes(scss, true),
+            vec![
+                (2, "card".into()),
+                (3, "card-active".into()),
+                (4, "card-title".into()),
+                (5, "card-link".into())
+            ]
+        );
+    }
+}
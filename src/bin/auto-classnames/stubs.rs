@@ -0,0 +1,183 @@
+//! `auto-classnames stubs`: adds an empty rule for every generated class to a
+//! stylesheet next to each component file, so styling can start right away.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use swc_plugin_react_auto_classnames::{Config, GeneratedClass};
+
+use crate::{current_dir, read_config, source_files, transform, USAGE};
+
+const EXTENSIONS: &[&str] = &["css", "scss", "module.css", "module.scss"];
+
+struct Options {
+    extension: String,
+    config: Config,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        extension: "css".into(),
+        config: Config::default(),
+        paths: vec![],
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ext" => {
+                let extension = args.next().ok_or("--ext expects an extension")?;
+                if !EXTENSIONS.contains(&extension.as_str()) {
+                    return Err(format!("unsupported stylesheet extension `{}`", extension));
+                }
+                options.extension = extension;
+            }
+            "--config" => options.config = read_config(args.next())?,
+            flag if flag.starts_with("--") => return Err(format!("unknown option `{}`", flag)),
+            path => options.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if options.paths.is_empty() {
+        return Err("no paths given".into());
+    }
+    Ok(options)
+}
+
+/// Whether `css` already has a selector for `.class_name`.
+fn has_selector(css: &str, class_name: &str) -> bool {
+    let selector = format!(".{}", class_name);
+    css.match_indices(&selector).any(|(index, _)| {
+        !css[index + selector.len()..]
+            .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// Appends an empty rule to `css` for every class it doesn't mention yet.
+/// Returns `None` when nothing is missing.
+fn add_stubs(css: &str, generated: &[GeneratedClass], css_module: bool) -> Option<String> {
+    let mut missing: Vec<&str> = vec![];
+    for generated in generated {
+        // CSS modules are scoped to the file, so they are keyed by the element alone.
+        let class_name = if css_module {
+            &generated.component_class
+        } else {
+            &generated.class_name
+        };
+        if !has_selector(css, class_name) && !missing.contains(&class_name.as_str()) {
+            missing.push(class_name);
+        }
+    }
+    if missing.is_empty() {
+        return None;
+    }
+
+    let mut css = css.to_string();
+    if !css.is_empty() {
+        if !css.ends_with('\n') {
+            css.push('\n');
+        }
+        css.push('\n');
+    }
+    let stubs: Vec<String> = missing
+        .iter()
+        .map(|class_name| format!(".{} {{\n}}\n", class_name))
+        .collect();
+    css.push_str(&stubs.join("\n"));
+    Some(css)
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> ExitCode {
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let files = match source_files(&options.paths) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let cwd = current_dir();
+    let css_module = options.extension.starts_with("module.");
+
+    let mut failed = false;
+    for file in files {
+        let output = match transform(&file, &options.config, &cwd) {
+            Ok(Some(output)) if !output.generated.is_empty() => output,
+            Ok(_) => continue,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                failed = true;
+                continue;
+            }
+        };
+
+        let stylesheet = file.with_extension(&options.extension);
+        let existing = fs::read_to_string(&stylesheet).unwrap_or_default();
+        let Some(css) = add_stubs(&existing, &output.generated, css_module) else {
+            continue;
+        };
+        match fs::write(&stylesheet, css) {
+            Ok(()) => println!("wrote {}", stylesheet.display()),
+            Err(err) => {
+                eprintln!("error: {}: {}", stylesheet.display(), err);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use swc_core::common::DUMMY_SP;
+    use swc_plugin_react_auto_classnames::GeneratedClass;
+
+    use super::add_stubs;
+
+    fn generated(component_class: &str) -> GeneratedClass {
+        GeneratedClass {
+            component: String::new(),
+            class_name: format!("button-{}", component_class),
+            component_class: component_class.into(),
+            span: DUMMY_SP,
+        }
+    }
+
+    #[test]
+    fn adds_missing_rules_only() {
+        let existing = ".button-row {\n  display: flex;\n}\n.button-row-item { }";
+        let css = add_stubs(
+            existing,
+            &[generated("row"), generated("icon"), generated("row-item")],
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            css,
+            ".button-row {\n  display: flex;\n}\n.button-row-item { }\n\n.button-icon {\n}\n"
+        );
+        assert_eq!(add_stubs(&css, &[generated("icon")], false), None);
+    }
+
+    #[test]
+    fn css_modules_use_component_keys() {
+        assert_eq!(
+            add_stubs("", &[generated("row"), generated("icon")], true).unwrap(),
+            ".row {\n}\n\n.icon {\n}\n"
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use globset::{Error as GlobError, GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::add_classname::HASH_LENGTH;
+
+/// Options accepted by the plugin, passed as the second element of the plugin
+/// tuple in the SWC configuration. Field names are camelCase in JSON, and every
+/// field is optional. Construct it with `Default` or serde and assign fields, as
+/// new options are added in minor releases.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+#[non_exhaustive]
+pub struct Config {
+    /// Globs of files to transform, relative to the project root. Empty means all files.
+    pub include: Vec<String>,
+    /// Globs of files to leave untouched, checked after `include`. Defaults to
+    /// tests, stories, mocks and generated files.
+    pub exclude: Vec<String>,
+    /// Namespace prepended to every generated class, e.g. `acme-`.
+    pub prefix: String,
+    /// Placed between the file and component parts of a class. Defaults to `-`.
+    pub separator: String,
+    /// Exact replacements for element names, applied before case conversion.
+    pub component_aliases: BTreeMap<String, String>,
+    /// Regex rewrites applied in order to element names that have no alias.
+    pub component_rules: Vec<RenameRule>,
+    /// Exact replacements for the file part, matched against the file stem.
+    pub file_aliases: BTreeMap<String, String>,
+    /// Regex rewrites applied in order to the file path before its stem is taken.
+    pub file_rules: Vec<RenameRule>,
+    /// File stems known to be shared by several files, e.g. `index` or `Table`.
+    /// Such files warn that their classes may collide unless `fileAliases` renames them.
+    pub ambiguous_stems: Vec<String>,
+    /// Longest class name to generate. Longer names are cut short and end with a
+    /// hash of the full name, so they stay unique. Must be at least the length
+    /// of `prefix` plus 7.
+    pub max_length: Option<usize>,
+    /// Where the generated class goes inside an existing `className` value.
+    pub class_position: ClassPosition,
+    /// Where a new `className` attribute is inserted among the element's attributes.
+    pub attribute_position: AttributePosition,
+    /// When the module default-imports a `*.module.css` (or `.scss`, `.sass`, `.less`)
+    /// file, also add that module's class for the element, e.g. `styles["text-field"]`.
+    pub css_modules: bool,
+    /// Export an object mapping each decorated component to its generated class, or
+    /// to an array of its classes when it got several. `true` uses the default
+    /// `__autoClassNames` export, a string names the export.
+    pub manifest_export: Option<ManifestExport>,
+    /// Append a JSON-lines record of each file's generated classes to this path.
+    /// The directory must be preopened for the plugin by the host. Records are
+    /// only ever appended, so the last one for a file wins.
+    pub manifest_file: Option<String>,
+    /// How to report elements that are left without a generated class, such as
+    /// ones with a dynamic `className` or a namespaced name. Off by default.
+    pub undecorated: Severity,
+    /// Props that add BEM modifier classes when set to `true` or a string literal,
+    /// e.g. `primary` and `size` give `file-button--primary file-button--size-lg`.
+    pub modifiers: Vec<String>,
+    /// Placed between the generated class and a modifier. Defaults to `--`.
+    pub modifier_separator: String,
+    /// Scope each element's class to its nearest decorated ancestors, keeping at most
+    /// this many, e.g. `card-header__title` for a `Title` inside a `Header`.
+    pub nesting_depth: Option<usize>,
+    /// Placed between the class parts of an element and its ancestors. Defaults to `__`.
+    pub element_separator: String,
+    /// Remove this file's generated classes from literal `className`s instead of
+    /// adding them, dropping attributes left empty. Undoes inlined classes.
+    pub strip: bool,
+    /// Options layered over the rest of the config for matching files, in order.
+    #[serde(skip_serializing)]
+    pub overrides: Vec<Override>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClassPosition {
+    /// `className="existing generated"`
+    Append,
+    /// `className="generated existing"`
+    Prepend,
+}
+
+impl ClassPosition {
+    pub fn join(self, existing: &str, class_name: &str) -> String {
+        match self {
+            ClassPosition::Append => format!("{} {}", existing, class_name),
+            ClassPosition::Prepend => format!("{} {}", class_name, existing),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttributePosition {
+    /// After every other attribute.
+    End,
+    /// Before every other attribute.
+    Start,
+    /// Right before the first `{...spread}`, so spread props can override it.
+    /// Falls back to the end when there is no spread.
+    BeforeSpread,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Off,
+    Warn,
+    Error,
+}
+
+/// A regex rewrite: `{ "pattern": "^Styled(.*)Root$", "replacement": "$1" }`.
+/// The replacement may refer to capture groups as `$1` or `${name}`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RenameRule {
+    #[serde(with = "regex_serde")]
+    pub pattern: Regex,
+    pub replacement: String,
+}
+
+impl RenameRule {
+    pub fn apply<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        self.pattern.replace_all(value, self.replacement.as_str())
+    }
+}
+
+mod regex_serde {
+    use regex::Regex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(regex.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map_err(D::Error::custom)
+    }
+}
+
+/// An `overrides` entry: `{ "files": "packages/ui/**", "options": { ... } }`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Override {
+    /// One glob or a list of globs, matched like `include`.
+    pub files: Globs,
+    /// Any config options except `overrides`. Objects are merged key by key,
+    /// everything else replaces the base value.
+    pub options: Map<String, Value>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Globs {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Globs {
+    fn as_slice(&self) -> &[String] {
+        match self {
+            Globs::One(glob) => std::slice::from_ref(glob),
+            Globs::Many(globs) => globs,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Glob(GlobError),
+    Options(serde_json::Error),
+    /// `maxLength` leaves no room for the prefix, a character and the hash.
+    MaxLength {
+        max_length: usize,
+        min: usize,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Glob(err) => write!(f, "invalid glob: {}", err),
+            ConfigError::Options(err) => write!(f, "invalid options: {}", err),
+            ConfigError::MaxLength { max_length, min } => write!(
+                f,
+                "`maxLength` is {} but must be at least {} to fit the prefix and hash",
+                max_length, min
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<GlobError> for ConfigError {
+    fn from(err: GlobError) -> Self {
+        ConfigError::Glob(err)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(err: serde_json::Error) -> Self {
+        ConfigError::Options(err)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            include: Vec::new(),
+            exclude: DEFAULT_EXCLUDE
+                .iter()
+                .map(|glob| glob.to_string())
+                .collect(),
+            prefix: String::new(),
+            separator: "-".to_string(),
+            component_aliases: BTreeMap::new(),
+            component_rules: Vec::new(),
+            file_aliases: BTreeMap::new(),
+            file_rules: Vec::new(),
+            ambiguous_stems: Vec::new(),
+            max_length: None,
+            class_position: ClassPosition::Append,
+            attribute_position: AttributePosition::End,
+            css_modules: false,
+            manifest_export: None,
+            manifest_file: None,
+            undecorated: Severity::Off,
+            modifiers: Vec::new(),
+            modifier_separator: "--".to_string(),
+            nesting_depth: None,
+            element_separator: "__".to_string(),
+            strip: false,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+pub const DEFAULT_EXCLUDE: &[&str] = &[
+    "**/*.test.*",
+    "**/*.spec.*",
+    "**/*.stories.*",
+    "**/__tests__/**",
+    "**/__mocks__/**",
+    "**/*.generated.*",
+];
+
+impl Config {
+    /// Whether `file_path` passes the `include` and `exclude` globs. Absolute paths
+    /// under `cwd` are matched relative to it.
+    pub fn applies_to(&self, file_path: &str, cwd: &str) -> Result<bool, ConfigError> {
+        let path = relative_path(file_path, cwd);
+
+        if !self.include.is_empty() && !glob_set(&self.include)?.is_match(path) {
+            return Ok(false);
+        }
+        Ok(!glob_set(&self.exclude)?.is_match(path))
+    }
+
+    /// Layers every override whose `files` match `file_path` over this config,
+    /// in the order they are listed, like ESLint's `overrides`, and checks the result.
+    pub fn resolve(&self, file_path: &str, cwd: &str) -> Result<Config, ConfigError> {
+        let path = relative_path(file_path, cwd);
+
+        let mut resolved = None;
+        for o in &self.overrides {
+            if !glob_set(o.files.as_slice())?.is_match(path) {
+                continue;
+            }
+            let options = match &mut resolved {
+                Some(options) => options,
+                None => resolved.insert(serde_json::to_value(self)?),
+            };
+            merge(options, &o.options);
+        }
+
+        let config: Config = match resolved {
+            Some(Value::Object(mut options)) => {
+                options.remove("overrides");
+                serde_json::from_value(Value::Object(options))?
+            }
+            _ => self.clone(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks options that are well-formed JSON but can't produce valid classes,
+    /// like a `maxLength` too small for the prefix and hash. [`Config::resolve`]
+    /// calls it; embedders building a config by hand should call it before
+    /// passing the config to [`crate::auto_classnames`].
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(max_length) = self.max_length {
+            // The prefix, a character of the name and the hash, plus the dash
+            // before the hash whenever there is room for it.
+            let min = self.prefix.chars().count() + HASH_LENGTH + 1;
+            if max_length < min {
+                return Err(ConfigError::MaxLength { max_length, min });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Strips `cwd` and any leading `./` so globs match project-relative paths.
+pub(crate) fn relative_path<'a>(file_path: &'a str, cwd: &str) -> &'a Path {
+    let path = Path::new(file_path);
+    let path = path.strip_prefix(cwd).unwrap_or(path);
+    path.strip_prefix(".").unwrap_or(path)
+}
+
+fn merge(base: &mut Value, options: &Map<String, Value>) {
+    let Value::Object(base) = base else {
+        return;
+    };
+    for (key, value) in options {
+        match (base.get_mut(key), value) {
+            (Some(existing @ Value::Object(_)), Value::Object(value)) => merge(existing, value),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Compiles globs with `*` stopping at path separators, as in `.gitignore` or ESLint.
+fn glob_set(globs: &[String]) -> Result<GlobSet, GlobError> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(GlobBuilder::new(glob).literal_separator(true).build()?);
+    }
+    builder.build()
+}
+
+/// Either `true`/`false` or the name of the manifest export.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ManifestExport {
+    Enabled(bool),
+    Named(String),
+}
+
+impl ManifestExport {
+    pub const DEFAULT_NAME: &'static str = "__autoClassNames";
+
+    /// The name of the export to inject, or `None` when the manifest is disabled.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ManifestExport::Enabled(true) => Some(Self::DEFAULT_NAME),
+            ManifestExport::Enabled(false) => None,
+            ManifestExport::Named(name) => Some(name),
+        }
+    }
+}
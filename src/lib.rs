@@ -1,34 +1,113 @@
+//! SWC transform that adds a `className` derived from the file and element name
+//! to every React element.
+//!
+//! Besides the WASM plugin entry point, the transform can be embedded in other
+//! Rust passes through [`auto_classnames`]:
+//!
+//! ```
+//! use swc_core::ecma::visit::Fold;
+//! use swc_plugin_react_auto_classnames::{auto_classnames, Config};
+//!
+//! fn pass(file_path: &str) -> impl Fold {
+//!     auto_classnames(file_path, Config::default())
+//! }
+//! ```
+
 mod add_classname;
+mod config;
+mod manifest;
+// Helpers for the `auto-classnames` binary, which has to reach them through the
+// library. Not part of the plugin's stable API.
+#[doc(hidden)]
+pub mod transform;
 
 use swc_core::plugin::{plugin_transform, proxies::TransformPluginProgramMetadata};
 use swc_core::{
+    common::errors::HANDLER,
     ecma::{
         ast::Program,
-        visit::{as_folder, FoldWith},
+        visit::{as_folder, Fold, VisitMut, VisitMutWith},
     },
     plugin::metadata::TransformPluginMetadataContextKind,
 };
 
-use add_classname::AddClassnameVisitor;
+pub use add_classname::{AddClassnameVisitor, GeneratedClass};
+pub use config::{
+    AttributePosition, ClassPosition, Config, ConfigError, Globs, ManifestExport, Override,
+    RenameRule, Severity,
+};
+
+/// Creates the transform for the module at `file_path`, usable both as a `Fold`
+/// and as a `VisitMut` pass. The file stem is the first part of every class name.
+/// `config` should have passed [`Config::validate`].
+pub fn auto_classnames(file_path: &str, config: Config) -> impl Fold + VisitMut {
+    as_folder(AddClassnameVisitor::new(file_path, config))
+}
 
 #[plugin_transform]
-pub fn process_transform(program: Program, data: TransformPluginProgramMetadata) -> Program {
-    let filepath = match data.get_context(&TransformPluginMetadataContextKind::Filename) {
-        Some(s) => s,
-        None => String::from(""),
-    };
-    program.fold_with(&mut as_folder(AddClassnameVisitor::new(&filepath)))
+pub fn process_transform(mut program: Program, data: TransformPluginProgramMetadata) -> Program {
+    let filepath = data
+        .get_context(&TransformPluginMetadataContextKind::Filename)
+        .unwrap_or_default();
+    let config: Config = data
+        .get_transform_plugin_config()
+        .map(|json| {
+            serde_json::from_str(&json)
+                .expect("invalid config for swc-plugin-react-auto-classnames")
+        })
+        .unwrap_or_default();
+    let cwd = data
+        .get_context(&TransformPluginMetadataContextKind::Cwd)
+        .unwrap_or_default();
+
+    let config = config
+        .resolve(&filepath, &cwd)
+        .expect("invalid config or overrides for swc-plugin-react-auto-classnames");
+    let applies = config
+        .applies_to(&filepath, &cwd)
+        .expect("invalid include/exclude glob for swc-plugin-react-auto-classnames");
+    if !applies {
+        return program;
+    }
+
+    let manifest_file = config.manifest_file.clone();
+
+    let mut visitor = AddClassnameVisitor::new(&filepath, config);
+    program.visit_mut_with(&mut visitor);
+
+    if let Some(manifest_file) = manifest_file {
+        if !visitor.generated().is_empty() {
+            if let Err(err) =
+                manifest::append_record(&manifest_file, &filepath, &cwd, visitor.generated())
+            {
+                HANDLER.with(|handler| {
+                    handler.warn(&format!(
+                        "react-auto-classnames: failed to write manifest to {}: {}",
+                        manifest_file, err
+                    ))
+                });
+            }
+        }
+    }
+
+    program
 }
 
 #[cfg(test)]
 mod test {
-    use swc_core::common::{chain, Mark};
+    use std::sync::{Arc, Mutex};
+
+    use swc_core::common::errors::{DiagnosticBuilder, Emitter, Handler, HANDLER};
+    use swc_core::common::{chain, Mark, DUMMY_SP};
+    use swc_core::ecma::ast::{
+        Expr, JSXAttrOrSpread, JSXAttrValue, JSXElement, Lit, ModuleItem, Stmt,
+    };
     use swc_core::ecma::transforms::base::resolver;
     use swc_core::ecma::transforms::testing::Tester;
     use swc_core::ecma::{
         parser::{Syntax, TsConfig},
         transforms::testing::test_inline,
-        visit::{as_folder, Fold},
+        visit::{Fold, VisitMutWith},
     };
 
     const SYNTAX: Syntax = Syntax::Typescript(TsConfig {
@@ -42,8 +121,874 @@ mod test {
     fn runner(_: &mut Tester) -> impl Fold {
         chain!(
             resolver(Mark::new(), Mark::new(), false),
-            as_folder(super::AddClassnameVisitor::new("lib/File_Name.tsx"))
+            super::auto_classnames("lib/File_Name.tsx", Default::default())
+        )
+    }
+
+    /// Parses `src` as a single JSX expression statement and runs the visitor over it.
+    fn transform_element(src: &str) -> Box<JSXElement> {
+        Tester::run(|tester| {
+            let mut module =
+                tester.with_parser("lib/File_Name.tsx", SYNTAX, src, |p| p.parse_module())?;
+            module.visit_mut_with(&mut super::auto_classnames(
+                "lib/File_Name.tsx",
+                Default::default(),
+            ));
+
+            match module.body.pop() {
+                Some(ModuleItem::Stmt(Stmt::Expr(stmt))) => match *stmt.expr {
+                    Expr::JSXElement(element) => Ok(element),
+                    _ => Err(()),
+                },
+                _ => Err(()),
+            }
+        })
+    }
+
+    #[derive(Clone, Default)]
+    struct Diagnostics(Arc<Mutex<Vec<String>>>);
+
+    impl Emitter for Diagnostics {
+        fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{:?}: {}", db.level, db.message()));
+        }
+    }
+
+    /// Runs the visitor over `src` and collects every diagnostic it emits.
+    fn diagnostics(src: &str, config: super::Config) -> Vec<String> {
+        let collected = Diagnostics::default();
+        let handler = Handler::with_emitter(true, false, Box::new(collected.clone()));
+
+        Tester::run(|tester| {
+            let mut module =
+                tester.with_parser("lib/File_Name.tsx", SYNTAX, src, |p| p.parse_module())?;
+            HANDLER.set(&handler, || {
+                module.visit_mut_with(&mut super::auto_classnames("lib/File_Name.tsx", config))
+            });
+            Ok(())
+        });
+
+        let collected = collected.0.lock().unwrap().clone();
+        collected
+    }
+
+    fn class_name_value(element: &JSXElement) -> &swc_core::ecma::ast::Str {
+        element
+            .opening
+            .attrs
+            .iter()
+            .find_map(|attr| match attr {
+                JSXAttrOrSpread::JSXAttr(attr) => match &attr.value {
+                    Some(JSXAttrValue::Lit(Lit::Str(value))) => Some(value),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .expect("className attribute")
+    }
+
+    #[test]
+    fn generated_attribute_uses_opening_element_span() {
+        let element = transform_element("<Component />;");
+        let attr = match element.opening.attrs.last() {
+            Some(JSXAttrOrSpread::JSXAttr(attr)) => attr,
+            _ => panic!("expected a generated className attribute"),
+        };
+
+        assert!(!element.opening.span.is_dummy());
+        assert_eq!(attr.span, element.opening.span);
+        assert_eq!(class_name_value(&element).span, element.opening.span);
+    }
+
+    #[test]
+    fn merged_literal_keeps_original_span() {
+        let src = r#"<Component className="no-print" />;"#;
+        let element = transform_element(src);
+        let value = class_name_value(&element);
+
+        // Byte positions are 1-based within the source map; the literal starts
+        // right after `className=`.
+        let start = src.find('"').unwrap() as u32 + 1;
+        assert_eq!(value.span.lo.0, start);
+        assert_eq!(value.span.hi.0, start + "\"no-print\"".len() as u32);
+        assert_eq!(&*value.value, "no-print file-name-component");
+        assert_eq!(
+            value.raw.as_deref(),
+            Some("\"no-print file-name-component\"")
+        );
+    }
+
+    fn manifest_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config = serde_json::from_str(r#"{ "manifestExport": true }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    fn named_manifest_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "manifestExport": "classNames" }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        manifest_runner,
+        /* Name */ manifest_export,
+        /* Input */
+        r#"
+        export const LoginTextField = () => (
+          <Row>
+            <TextField className="no-print" />
+            <TextField />
+            <Icons.Search />
+            <my-element />
+            <div className={dynamic} />
+          </Row>
+        );
+        "#,
+        /* Output */
+        r#"
+        export const LoginTextField = () =>
+          <Row className="file-name-row">
+            <TextField className="no-print file-name-text-field" />
+            <TextField className="file-name-text-field" />
+            <Icons.Search className="file-name-search" />
+            <my-element className="file-name-my-element" />
+            <div className={dynamic} />
+          </Row>;
+        export const __autoClassNames = {
+          Row: "file-name-row",
+          TextField: "file-name-text-field",
+          Search: "file-name-search",
+          "my-element": "file-name-my-element"
+        };
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        named_manifest_runner,
+        /* Name */ manifest_export_named,
+        /* Input */ r#"
+        const MyComponent = () => <Component />;
+        "#,
+        /* Output */
+        r#"
+        const MyComponent = () => <Component className="file-name-component" />;
+        export const classNames = {
+          Component: "file-name-component"
+        };
+        "#
+    );
+
+    fn nested_manifest_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "manifestExport": true, "nestingDepth": 1 }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        nested_manifest_runner,
+        /* Name */ manifest_export_lists_every_class_of_a_component,
+        /* Input */
+        r#"
+        <Card>
+          <Header><Title /></Header>
+          <Footer><Title /></Footer>
+        </Card>;
+        "#,
+        /* Output */
+        r#"
+        <Card className="file-name-card">
+          <Header className="file-name-card__header"><Title className="file-name-header__title" /></Header>
+          <Footer className="file-name-card__footer"><Title className="file-name-footer__title" /></Footer>
+        </Card>;
+        export const __autoClassNames = {
+          Card: "file-name-card",
+          Header: "file-name-card__header",
+          Title: ["file-name-header__title", "file-name-footer__title"],
+          Footer: "file-name-card__footer"
+        };
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        manifest_runner,
+        /* Name */ manifest_export_skipped_without_jsx,
+        /* Input */ r#"
+        export const answer = 42;
+        "#,
+        /* Output */
+        r#"
+        export const answer = 42;
+        "#
+    );
+
+    #[test]
+    fn manifest_record_line() {
+        let generated = vec![
+            super::add_classname::GeneratedClass {
+                component: "TextField".into(),
+                class_name: "file-name-text-field".into(),
+                component_class: "text-field".into(),
+                span: DUMMY_SP,
+            },
+            super::add_classname::GeneratedClass {
+                component: "Row".into(),
+                class_name: "file-name-row".into(),
+                component_class: "row".into(),
+                span: DUMMY_SP,
+            },
+            super::add_classname::GeneratedClass {
+                component: "TextField".into(),
+                class_name: "file-name-row__text-field".into(),
+                component_class: "row__text-field".into(),
+                span: DUMMY_SP,
+            },
+        ];
+
+        assert_eq!(
+            super::manifest::record_line("lib/File_Name.tsx", &generated),
+            concat!(
+                r#"{"classes":{"Row":"file-name-row","#,
+                r#""TextField":["file-name-text-field","file-name-row__text-field"]},"#,
+                r#""file":"lib/File_Name.tsx"}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn manifest_append_record() {
+        let path = std::env::temp_dir().join(format!(
+            "react-auto-classnames-manifest-{}.jsonl",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        super::manifest::append_record(path, "a.tsx", "", &[]).unwrap();
+        super::manifest::append_record(path, "/home/app/src/b.tsx", "/home/app", &[]).unwrap();
+
+        let contents = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            contents,
+            "{\"classes\":{},\"file\":\"a.tsx\"}\n{\"classes\":{},\"file\":\"src/b.tsx\"}\n"
+        );
+    }
+
+    fn unnamed_file_runner(_: &mut Tester) -> impl Fold {
+        super::auto_classnames("", Default::default())
+    }
+
+    test_inline!(
+        SYNTAX,
+        unnamed_file_runner,
+        /* Name */ unnamed_file,
+        /* Input */ r#"
+        const MyComponent = () => <TextField />;
+        "#,
+        /* Output */
+        r#"
+        const MyComponent = () => <TextField className="text-field" />;
+        "#
+    );
+
+    #[test]
+    fn default_excludes_skip_tests_and_stories() {
+        let config = super::Config::default();
+
+        assert!(config.applies_to("src/Button.tsx", "").unwrap());
+        assert!(!config.applies_to("src/Button.test.tsx", "").unwrap());
+        assert!(!config.applies_to("Button.stories.tsx", "").unwrap());
+        assert!(!config
+            .applies_to("/repo/src/__mocks__/Button.tsx", "/repo")
+            .unwrap());
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        let config: super::Config = serde_json::from_str(
+            r#"{ "include": ["src/**/*.tsx"], "exclude": ["src/legacy/**"] }"#,
+        )
+        .unwrap();
+
+        assert!(config.applies_to("/repo/src/Button.tsx", "/repo").unwrap());
+        assert!(config.applies_to("src/Button.test.tsx", "").unwrap());
+        assert!(!config.applies_to("src/legacy/Old.tsx", "").unwrap());
+        assert!(!config.applies_to("lib/Button.tsx", "").unwrap());
+        assert!(!config.applies_to("src/Button.jsx", "").unwrap());
+    }
+
+    #[test]
+    fn overrides_layer_over_base_config() {
+        let config: super::Config = serde_json::from_str(
+            r#"{
+                "manifestExport": "classNames",
+                "overrides": [
+                    { "files": "packages/ui/**", "options": { "manifestExport": true } },
+                    {
+                        "files": ["packages/ui/legacy/**", "apps/**"],
+                        "options": { "exclude": [], "manifestFile": "classes.jsonl" }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let ui = config
+            .resolve("/repo/packages/ui/Button.tsx", "/repo")
+            .unwrap();
+        assert_eq!(ui.manifest_export.unwrap().name(), Some("__autoClassNames"));
+        assert!(ui.manifest_file.is_none());
+        assert!(!ui.exclude.is_empty());
+
+        let legacy = config.resolve("./packages/ui/legacy/Old.tsx", "").unwrap();
+        assert_eq!(
+            legacy.manifest_export.unwrap().name(),
+            Some("__autoClassNames")
+        );
+        assert_eq!(legacy.manifest_file.as_deref(), Some("classes.jsonl"));
+        assert!(legacy.exclude.is_empty());
+
+        let app = config.resolve("packages/app/Page.tsx", "").unwrap();
+        assert_eq!(app.manifest_export.unwrap().name(), Some("classNames"));
+    }
+
+    fn prefix_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "prefix": "acme-", "separator": "__" }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        prefix_runner,
+        /* Name */ prefix_and_separator,
+        /* Input */
+        r#"
+        const MyComponent = () => <TextField className="no-print" />;
+        "#,
+        /* Output */
+        r#"
+        const MyComponent = () => <TextField className="no-print acme-file-name__text-field" />;
+        "#
+    );
+
+    fn rename_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config = serde_json::from_str(
+            r#"{
+                "componentAliases": { "AgGridReact": "Grid" },
+                "componentRules": [
+                    { "pattern": "^StyledMui(\\w+)Root$", "replacement": "$1" },
+                    { "pattern": "^Grid", "replacement": "Table" }
+                ],
+                "fileAliases": { "Login_Form": "login" },
+                "fileRules": [{ "pattern": "([^/]+)/index\\.tsx$", "replacement": "$1.tsx" }]
+            }"#,
+        )
+        .unwrap();
+        super::auto_classnames("src/Login_Form/index.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        rename_runner,
+        /* Name */ component_and_file_renames,
+        /* Input */
+        r#"
+        <>
+          <AgGridReact />
+          <StyledMuiTextFieldRoot />
+          <GridCell />
+        </>;
+        "#,
+        /* Output */
+        r#"
+        <>
+          <AgGridReact className="login-grid" />
+          <StyledMuiTextFieldRoot className="login-text-field" />
+          <GridCell className="login-table-cell" />
+        </>;
+        "#
+    );
+
+    #[test]
+    fn invalid_rename_rule_is_a_config_error() {
+        let err = serde_json::from_str::<super::Config>(
+            r#"{ "componentRules": [{ "pattern": "(", "replacement": "" }] }"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("regex parse error"), "{}", err);
+    }
+
+    #[test]
+    fn max_length_keeps_prefix() {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "prefix": "acme-", "maxLength": 12 }"#).unwrap();
+        let config = config.resolve("lib/File_Name.tsx", "").unwrap();
+        let output = super::transform::transform_file(
+            "lib/File_Name.tsx",
+            "<><ConfirmationDialog /><ConfirmationDialogFooter /></>;",
+            &config,
+        )
+        .unwrap();
+        let class_names: Vec<&str> = output
+            .generated
+            .iter()
+            .map(|generated| generated.class_name.as_str())
+            .collect();
+        assert_eq!(class_names, vec!["acme-flw3njt", "acme-fbo4c8f"]);
+
+        let config: super::Config =
+            serde_json::from_str(r#"{ "prefix": "acme-", "maxLength": 11 }"#).unwrap();
+        assert!(config.validate().is_err());
+        assert_eq!(
+            config
+                .resolve("lib/File_Name.tsx", "")
+                .unwrap_err()
+                .to_string(),
+            "`maxLength` is 11 but must be at least 12 to fit the prefix and hash"
+        );
+    }
+
+    #[test]
+    fn modifiers_are_recorded_and_truncated() {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "maxLength": 16, "modifiers": ["size"] }"#).unwrap();
+        let output =
+            super::transform::transform_file("lib/File_Name.tsx", r#"<Row size="lg" />;"#, &config)
+                .unwrap();
+        let classes: Vec<(&str, &str)> = output
+            .generated
+            .iter()
+            .map(|generated| {
+                (
+                    generated.class_name.as_str(),
+                    generated.component_class.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            classes,
+            vec![
+                ("file-name-row", "row"),
+                ("file-name-r83x1v", "row--size-lg")
+            ]
+        );
+        assert!(classes.iter().all(|(class_name, _)| class_name.len() <= 16));
+        assert!(output
+            .code
+            .contains(r#"className="file-name-row file-name-r83x1v""#));
+    }
+
+    fn max_length_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config = serde_json::from_str(r#"{ "maxLength": 20 }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        max_length_runner,
+        /* Name */ max_length_truncates_with_hash,
+        /* Input */
+        r#"
+        <>
+          <Row />
+          <ConfirmationDialogHeader />
+          <ConfirmationDialogFooter />
+        </>;
+        "#,
+        /* Output */
+        r#"
+        <>
+          <Row className="file-name-row" />
+          <ConfirmationDialogHeader className="file-name-con-2wr3zo" />
+          <ConfirmationDialogFooter className="file-name-con-kxskbx" />
+        </>;
+        "#
+    );
+
+    fn prepend_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config = serde_json::from_str(
+            r#"{ "classPosition": "prepend", "attributePosition": "beforeSpread" }"#,
+        )
+        .unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    fn start_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "attributePosition": "start" }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        prepend_runner,
+        /* Name */ prepend_class_and_insert_before_spread,
+        /* Input */
+        r#"
+        <>
+          <A className='no-print' />
+          <B className={"no-print"} />
+          <C id="c" {...props} {...rest} />
+          <D id="d" />
+        </>;
+        "#,
+        /* Output */
+        r#"
+        <>
+          <A className='file-name-a no-print' />
+          <B className="file-name-b no-print" />
+          <C id="c" className="file-name-c" {...props} {...rest} />
+          <D id="d" className="file-name-d" />
+        </>;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        start_runner,
+        /* Name */ insert_attribute_at_start,
+        /* Input */ r#"
+        <C id="c" {...props} />;
+        "#,
+        /* Output */
+        r#"
+        <C className="file-name-c" id="c" {...props} />;
+        "#
+    );
+
+    fn modifiers_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "modifiers": ["primary", "size", "isActive"] }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        modifiers_runner,
+        /* Name */ modifiers_from_static_props,
+        /* Input */
+        r#"
+          <>
+            <Button primary size="lg" />
+            <Button className="wide" isActive={true} size={"x large"} />
+            <Button primary={false} size={size} disabled />
+          </>;
+        "#,
+        /* Output */
+        r#"
+          <>
+            <Button primary size="lg" className="file-name-button file-name-button--primary file-name-button--size-lg" />
+            <Button className="wide file-name-button file-name-button--is-active file-name-button--size-x-large" isActive={true} size={"x large"} />
+            <Button primary={false} size={size} disabled className="file-name-button" />
+          </>;
+        "#
+    );
+
+    fn nesting_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "separator": "__", "nestingDepth": 2 }"#).unwrap();
+        super::auto_classnames("lib/Card.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        nesting_runner,
+        /* Name */ nesting_scopes_children_to_ancestors,
+        /* Input */
+        r#"
+          <Header>
+            <Title>
+              <Icon>
+                <Path />
+              </Icon>
+            </Title>
+            <Dynamic className={cx()}>
+              <Badge />
+            </Dynamic>
+            <React.Fragment>
+              <Subtitle />
+            </React.Fragment>
+          </Header>;
+        "#,
+        /* Output */
+        r#"
+          <Header className="card__header">
+            <Title className="card__header__title">
+              <Icon className="card__header__title__icon">
+                <Path className="card__title__icon__path" />
+              </Icon>
+            </Title>
+            <Dynamic className={cx()}>
+              <Badge className="card__header__badge" />
+            </Dynamic>
+            <React.Fragment>
+              <Subtitle className="card__header__subtitle" />
+            </React.Fragment>
+          </Header>;
+        "#
+    );
+
+    fn strip_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config = serde_json::from_str(r#"{ "strip": true }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        strip_runner,
+        /* Name */ strip_removes_generated_classes,
+        /* Input */
+        r#"
+          <>
+            <Header className="file-name-header" />
+            <Row className="row file-name-row" />
+            <Cell className={"file-name-cell--wide file-name-cell wide"} />
+            <Avatar className="other-file-avatar" />
+            <Spacer className="" />
+            <Item className={cx("file-name-item")} />
+            <Group className="file-name-group file-name-group-title" />
+          </>;
+        "#,
+        /* Output */
+        r#"
+          <>
+            <Header />
+            <Row className="row" />
+            <Cell className={"wide"} />
+            <Avatar className="other-file-avatar" />
+            <Spacer className="" />
+            <Item className={cx("file-name-item")} />
+            <Group className="file-name-group-title" />
+          </>;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        strip_runner,
+        /* Name */ strip_keeps_quotes,
+        /* Input */
+        r#"
+          <>
+            <Row className='row file-name-row' />
+            <Cell className={'file-name-cell wide'} />
+          </>;
+        "#,
+        /* Output */
+        r#"
+          <>
+            <Row className='row' />
+            <Cell className={'wide'} />
+          </>;
+        "#
+    );
+
+    fn strip_prefixed_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "strip": true, "prefix": "acme-", "nestingDepth": 1 }"#)
+                .unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        strip_prefixed_runner,
+        /* Name */ strip_removes_renamed_classes_with_a_prefix,
+        /* Input */
+        r#"
+          <Header className="acme-file-name-header">
+            <Title className="acme-file-name-header__title" />
+            <Cell className="acme-file-name-old-cell file-name-cell" />
+          </Header>;
+        "#,
+        /* Output */
+        r#"
+          <Header>
+            <Title />
+            <Cell className="file-name-cell" />
+          </Header>;
+        "#
+    );
+
+    fn css_modules_runner(_: &mut Tester) -> impl Fold {
+        let config: super::Config = serde_json::from_str(r#"{ "cssModules": true }"#).unwrap();
+        super::auto_classnames("lib/File_Name.tsx", config)
+    }
+
+    test_inline!(
+        SYNTAX,
+        css_modules_runner,
+        /* Name */ css_module_class_appended,
+        /* Input */
+        r#"
+        import styles from "./File_Name.module.css";
+
+        <>
+          <TextField className="no-print" />
+          <Row />
+          <Cell className={cx(active)} />
+        </>;
+        "#,
+        /* Output */
+        r#"
+        import styles from "./File_Name.module.css";
+
+        <>
+          <TextField className={["no-print file-name-text-field", styles["text-field"]].join(" ")} />
+          <Row className={["file-name-row", styles["row"]].join(" ")} />
+          <Cell className={[cx(active), styles["cell"]].join(" ")} />
+        </>;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        css_modules_runner,
+        /* Name */ css_module_namespace_import,
+        /* Input */
+        r#"
+        import * as classes from "./theme.module.scss";
+
+        <Row />;
+        "#,
+        /* Output */
+        r#"
+        import * as classes from "./theme.module.scss";
+
+        <Row className={["file-name-row", classes["row"]].join(" ")} />;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        css_modules_runner,
+        /* Name */ css_modules_without_import,
+        /* Input */ r#"
+        import "./global.css";
+
+        <Row />;
+        "#,
+        /* Output */
+        r#"
+        import "./global.css";
+
+        <Row className="file-name-row" />;
+        "#
+    );
+
+    #[test]
+    fn transform_file_reports_changes() {
+        let output = super::transform::transform_file(
+            "lib/File_Name.tsx",
+            "// keep me\nconst MyComponent = () => <Component />;\n",
+            &Default::default(),
         )
+        .unwrap();
+
+        assert!(output.changed);
+        assert_eq!(
+            output.code,
+            "// keep me\nconst MyComponent = ()=><Component className=\"file-name-component\"/>;\n"
+        );
+        assert_eq!(output.generated[0].class_name, "file-name-component");
+
+        let output = super::transform::transform_file(
+            "lib/plain.ts",
+            "export const a = 1;\n",
+            &Default::default(),
+        )
+        .unwrap();
+        assert!(!output.changed);
+    }
+
+    #[test]
+    fn eject_file_edits_only_class_names() {
+        let source = r#"// keep me
+export const Card = ({ title, ...rest }) => (
+  <section   data-x="1">
+    <Header className='big' {...rest}>{title}</Header>
+    <Body<string> />
+    <Icon className={"a" + "b"} />
+    <Dyn className={cx(a)} />
+  </section>
+);
+"#;
+        let config: super::Config =
+            serde_json::from_str(r#"{ "manifestExport": "classes" }"#).unwrap();
+        let output = super::transform::eject_file("lib/File_Name.tsx", source, &config).unwrap();
+
+        assert!(output.changed);
+        assert_eq!(
+            output.code,
+            r#"// keep me
+export const Card = ({ title, ...rest }) => (
+  <section   data-x="1" className="file-name-section">
+    <Header className='big file-name-header' {...rest}>{title}</Header>
+    <Body<string> className="file-name-body" />
+    <Icon className="ab file-name-icon" />
+    <Dyn className={cx(a)} />
+  </section>
+);
+export const classes = {
+    section: "file-name-section",
+    Header: "file-name-header",
+    Body: "file-name-body",
+    Icon: "file-name-icon"
+};
+"#
+        );
+        let again = super::transform::eject_file("lib/File_Name.tsx", &output.code, &config);
+        assert!(!again.unwrap().changed);
+
+        let config: super::Config = serde_json::from_str(
+            r#"{ "cssModules": true, "manifestExport": "classes", "modifiers": ["primary"] }"#,
+        )
+        .unwrap();
+        let source = "import styles from \"./File_Name.module.css\";\n\
+                      <Row primary className=\"wide\" />;\n";
+        let once = super::transform::eject_file("lib/File_Name.tsx", source, &config).unwrap();
+        let twice = super::transform::eject_file("lib/File_Name.tsx", &once.code, &config).unwrap();
+        assert!(once.changed);
+        assert!(!twice.changed, "{}", twice.code);
+
+        let config: super::Config =
+            serde_json::from_str(r#"{ "attributePosition": "beforeSpread" }"#).unwrap();
+        let output =
+            super::transform::eject_file("lib/File_Name.tsx", "<A {...rest} />;", &config).unwrap();
+        assert_eq!(output.code, r#"<A className="file-name-a" {...rest} />;"#);
+
+        let output = super::transform::eject_file(
+            "lib/File_Name.tsx",
+            r#"<A id="a" {...rest} />;"#,
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            output.code,
+            r#"<A id="a" {...rest} className="file-name-a" />;"#
+        );
+    }
+
+    #[test]
+    fn transform_file_reports_parse_errors() {
+        let err = super::transform::transform_file(
+            "lib/File_Name.jsx",
+            "const a = <div>;\n",
+            &Default::default(),
+        )
+        .err()
+        .unwrap();
+
+        assert!(err.starts_with("lib/File_Name.jsx:1:"), "{}", err);
     }
 
     test_inline!(
@@ -451,6 +1396,287 @@ mod test {
         "#
     );
 
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ jsx_element_and_fragment_attribute_values,
+        /* Input */
+        r#"
+          <Button icon=<Icon /> slot=<><Badge /></> />;
+        "#,
+        /* Output */
+        r#"
+          <Button icon=<Icon className="file-name-icon" /> slot=<><Badge className="file-name-badge" /></> className="file-name-button" />;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ arrow_attribute_decorated_once,
+        /* Input */
+        r#"
+          <List renderItem={() => <Item />} renderEmpty={() => { return <Empty />; }} />;
+        "#,
+        /* Output */
+        r#"
+          <List renderItem={() => <Item className="file-name-item" />} renderEmpty={() => { return <Empty className="file-name-empty" />; }} className="file-name-list" />;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ function_expression_attribute,
+        /* Input */
+        r#"
+          <List renderItem={function (item) { return <Item />; }} />;
+        "#,
+        /* Output */
+        r#"
+          <List renderItem={function (item) { return <Item className="file-name-item" />; }} className="file-name-list" />;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ spread_attribute_with_jsx,
+        /* Input */
+        r#"
+          <List {...{ renderItem: () => <Item />, footer: <Footer /> }} />;
+        "#,
+        /* Output */
+        r#"
+          <List {...{ renderItem: () => <Item className="file-name-item" />, footer: <Footer className="file-name-footer" /> }} className="file-name-list" />;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ namespaced_attribute_value,
+        /* Input */
+        r#"
+          <Layout slot:header={<Header />} />;
+        "#,
+        /* Output */
+        r#"
+          <Layout slot:header={<Header className="file-name-header" />} className="file-name-layout" />;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ attributes_after_class_name,
+        /* Input */
+        r#"
+          <Fragment key="a">
+            <List className="no-print" renderItem={() => <Item />} />
+          </Fragment>;
+        "#,
+        /* Output */
+        r#"
+          <Fragment key="a">
+            <List className="no-print file-name-list" renderItem={() => <Item className="file-name-item" />} />
+          </Fragment>;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ static_expression_class_names,
+        /* Input */
+        r#"
+          <>
+            <A className={"no-print"} />
+            <B className={`no-print`} />
+            <C className={"no-print " + `wide` + ("-x")} />
+            <D className={`no-print ${size}`} />
+            <E className={"no-print " + size} />
+          </>;
+        "#,
+        /* Output */
+        r#"
+          <>
+            <A className="no-print file-name-a" />
+            <B className="no-print file-name-b" />
+            <C className="no-print wide-x file-name-c" />
+            <D className={`no-print ${size}`} />
+            <E className={"no-print " + size} />
+          </>;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ multi_line_template_class_names,
+        /* Input */
+        r#"
+          <>
+            <A className={`
+              ag-theme-alpine
+              clickable-rows
+            `} />
+            <B className={"no-print\twide"} />
+            <C className={"a\\b"} />
+          </>;
+        "#,
+        /* Output */
+        r#"
+          <>
+            <A className="ag-theme-alpine clickable-rows file-name-a" />
+            <B className="no-print wide file-name-b" />
+            <C className={"a\\b file-name-c"} />
+          </>;
+        "#
+    );
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ valueless_and_nullish_class_names,
+        /* Input */
+        r#"
+          <>
+            <A className />
+            <B className={undefined} />
+            <C className={null} />
+            <D className={(null)} />
+          </>;
+        "#,
+        /* Output */
+        r#"
+          <>
+            <A className="file-name-a" />
+            <B className="file-name-b" />
+            <C className="file-name-c" />
+            <D className="file-name-d" />
+          </>;
+        "#
+    );
+
+    #[test]
+    fn valueless_class_name_warns() {
+        assert_eq!(
+            diagnostics(
+                "<><A className /><B className={null} /></>;",
+                Default::default()
+            ),
+            vec![
+                "Warning: `className` without a value renders as \"true\", \
+                 replaced it with the generated class"
+            ]
+        );
+    }
+
+    #[test]
+    fn colliding_element_names_warn() {
+        assert_eq!(
+            diagnostics(
+                r#"
+                  <>
+                    <TextField />
+                    <Text_Field />
+                    <Text_Field />
+                    <Layout.Panel />
+                    <Card.Panel />
+                    <Card.Panel />
+                  </>;
+                "#,
+                Default::default()
+            ),
+            vec![
+                "Warning: `TextField` and `Text_Field` both generate `file-name-text-field`, \
+                 so their styles can't be told apart",
+                "Warning: `Layout.Panel` and `Card.Panel` both generate `file-name-panel`, \
+                 so their styles can't be told apart",
+            ]
+        );
+
+        let config: super::Config =
+            serde_json::from_str(r#"{ "componentAliases": { "Txt": "TextField" } }"#).unwrap();
+        assert!(diagnostics("<><TextField /><Txt /></>;", config.clone()).is_empty());
+        assert!(diagnostics("<><Txt /><TextField /></>;", config.clone()).is_empty());
+        assert_eq!(
+            diagnostics("<><Txt /><TextField /><Text_Field /></>;", config),
+            vec![
+                "Warning: `TextField` and `Text_Field` both generate `file-name-text-field`, \
+                 so their styles can't be told apart"
+            ]
+        );
+    }
+
+    #[test]
+    fn undecorated_elements_are_reported() {
+        let src = r#"
+          <>
+            <A className={styles.a} />
+            <B className=<i /> />
+            <C className="c" />
+          </>;
+        "#;
+        assert!(diagnostics(src, Default::default()).is_empty());
+
+        let config: super::Config = serde_json::from_str(r#"{ "undecorated": "warn" }"#).unwrap();
+        assert_eq!(
+            diagnostics(src, config),
+            vec![
+                "Warning: no class generated: `A` has a `className` computed at runtime",
+                "Warning: no class generated: `B` has a `className` that is neither a string \
+                 nor an expression",
+            ]
+        );
+
+        let config: super::Config = serde_json::from_str(r#"{ "undecorated": "error" }"#).unwrap();
+        assert_eq!(
+            diagnostics("<A className={cx()} />;", config),
+            vec!["Error: no class generated: `A` has a `className` computed at runtime"]
+        );
+    }
+
+    #[test]
+    fn ambiguous_stem_warns_once() {
+        let config: super::Config =
+            serde_json::from_str(r#"{ "ambiguousStems": ["index", "File_Name"] }"#).unwrap();
+        assert_eq!(
+            diagnostics("<><A /><B /></>;", config),
+            vec![
+                "Warning: `File_Name` is listed in `ambiguousStems`, so `file-name-a` may \
+                 collide with classes from another file; add a `fileAliases` entry for it"
+            ]
+        );
+
+        let config: super::Config = serde_json::from_str(
+            r#"{ "ambiguousStems": ["File_Name"], "fileAliases": { "File_Name": "lib-file" } }"#,
+        )
+        .unwrap();
+        assert!(diagnostics("<A />;", config).is_empty());
+    }
+
+    test_inline!(
+        SYNTAX,
+        runner,
+        /* Name */ merged_literal_keeps_quotes_and_escapes,
+        /* Input */
+        r#"
+          <>
+            <A className='no-print' />
+            <B className="no-print&nbsp;wide" />
+          </>;
+        "#,
+        /* Output */
+        r#"
+          <>
+            <A className='no-print file-name-a' />
+            <B className="no-print&nbsp;wide file-name-b" />
+          </>;
+        "#
+    );
+
     test_inline!(
         SYNTAX,
         runner,
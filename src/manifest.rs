@@ -0,0 +1,51 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+
+use serde_json::{json, Map, Value};
+
+use crate::add_classname::{classes_by_component, GeneratedClass};
+use crate::config::relative_path;
+
+/// Maps each component to its class, or to an array of its classes in source
+/// order when it got more than one, e.g. under `nestingDepth`.
+pub fn classes_json(generated: &[GeneratedClass]) -> Map<String, Value> {
+    classes_by_component(generated)
+        .into_iter()
+        .map(|(component, classes)| {
+            let value = match classes.as_slice() {
+                [class_name] => json!(class_name),
+                classes => json!(classes),
+            };
+            (component.to_string(), value)
+        })
+        .collect()
+}
+
+/// Serializes the classes generated for one file as a single JSON-lines record,
+/// with keys in sorted order: `{"classes":{"Button":"button-button"},"file":"src/Button.tsx"}`.
+pub fn record_line(file: &str, generated: &[GeneratedClass]) -> String {
+    let classes = classes_json(generated);
+    let mut line = json!({ "file": file, "classes": classes }).to_string();
+    line.push('\n');
+    line
+}
+
+/// Appends the record for `file` to the manifest at `path`, creating it if needed.
+/// `file` is written relative to `cwd`, so the manifest is the same on every machine.
+/// The line is written with a single call so concurrent transforms don't interleave.
+/// Nothing is ever truncated: rebuilding a file adds another record for it, so
+/// readers keep the last record per `file`.
+pub fn append_record(
+    path: &str,
+    file: &str,
+    cwd: &str,
+    generated: &[GeneratedClass],
+) -> io::Result<()> {
+    let file = relative_path(file, cwd).to_string_lossy();
+    let line = record_line(&file, generated);
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
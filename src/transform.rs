@@ -0,0 +1,365 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use swc_core::common::comments::SingleThreadedComments;
+use swc_core::common::errors::{DiagnosticBuilder, Emitter as DiagnosticEmitter, Handler, HANDLER};
+use swc_core::common::{
+    sync::Lrc, BytePos, EqIgnoreSpan, FileName, SourceFile, SourceMap, Span, Spanned,
+};
+use swc_core::ecma::ast::{
+    EsVersion, JSXAttr, JSXAttrName, JSXAttrOrSpread, JSXAttrValue, JSXOpeningElement, Module,
+};
+use swc_core::ecma::codegen::{text_writer::JsWriter, Config as CodegenConfig, Emitter, Node};
+use swc_core::ecma::parser::{parse_file_as_module, EsConfig, Syntax, TsConfig};
+use swc_core::ecma::visit::{Visit, VisitMutWith, VisitWith};
+
+use crate::add_classname::{exports_const, AddClassnameVisitor, GeneratedClass};
+use crate::config::Config;
+pub use crate::manifest::classes_json;
+
+/// The result of running the visitor over a single source file outside of SWC.
+pub struct TransformOutput {
+    /// The transformed module, reprinted by the SWC code generator, or the edited
+    /// source for [`eject_file`].
+    pub code: String,
+    /// Whether the visitor modified the module at all.
+    pub changed: bool,
+    pub generated: Vec<GeneratedClass>,
+    /// Warnings and errors the visitor reported, as `path:line:column: level: message`.
+    pub diagnostics: Vec<String>,
+    source_map: Lrc<SourceMap>,
+}
+
+impl TransformOutput {
+    /// The 1-based line and column of `span`, e.g. of a [`GeneratedClass::span`].
+    pub fn position(&self, span: Span) -> (usize, usize) {
+        let loc = self.source_map.lookup_char_pos(span.lo);
+        (loc.line, loc.col_display + 1)
+    }
+}
+
+/// A diagnostic message with the span it points at, if any.
+type Diagnostic = (Option<Span>, String);
+
+/// Collects the diagnostics emitted while the visitor runs.
+#[derive(Clone, Default)]
+struct Diagnostics(Arc<Mutex<Vec<Diagnostic>>>);
+
+impl DiagnosticEmitter for Diagnostics {
+    fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+        let message = format!("{}: {}", db.level, db.message());
+        self.0
+            .lock()
+            .unwrap()
+            .push((db.span.primary_span(), message));
+    }
+}
+
+/// Picks the parser syntax from the file extension.
+pub fn syntax_for(file_path: &str) -> Option<Syntax> {
+    match Path::new(file_path).extension()?.to_str()? {
+        ext @ ("tsx" | "ts") => Some(Syntax::Typescript(TsConfig {
+            tsx: ext == "tsx",
+            ..Default::default()
+        })),
+        "jsx" | "js" => Some(Syntax::Es(EsConfig {
+            jsx: true,
+            ..Default::default()
+        })),
+        _ => None,
+    }
+}
+
+/// A parsed source file, with what is needed to print it or point into it.
+struct Parsed {
+    cm: Lrc<SourceMap>,
+    fm: Lrc<SourceFile>,
+    comments: SingleThreadedComments,
+    module: Module,
+}
+
+/// Parses `source` as a module, picking the syntax from `file_path`.
+fn parse(file_path: &str, source: &str) -> Result<Parsed, String> {
+    let syntax = syntax_for(file_path)
+        .ok_or_else(|| format!("{}: unsupported file extension", file_path))?;
+
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(FileName::Real(file_path.into()), source.into());
+    let comments = SingleThreadedComments::default();
+
+    let mut errors = vec![];
+    let parsed = parse_file_as_module(
+        &fm,
+        syntax,
+        EsVersion::latest(),
+        Some(&comments),
+        &mut errors,
+    );
+    match (parsed, errors.into_iter().next()) {
+        (Ok(module), None) => Ok(Parsed {
+            cm,
+            fm,
+            comments,
+            module,
+        }),
+        (Err(err), _) | (Ok(_), Some(err)) => {
+            let loc = cm.lookup_char_pos(err.span().lo);
+            Err(format!(
+                "{}:{}:{}: {}",
+                file_path,
+                loc.line,
+                loc.col_display + 1,
+                err.kind().msg()
+            ))
+        }
+    }
+}
+
+/// Applies `AddClassnameVisitor` to `module`, returning the generated classes
+/// and the formatted diagnostics. Diagnostics reported as errors fail the file,
+/// as they fail the build when running as a plugin.
+fn visit(
+    file_path: &str,
+    cm: &SourceMap,
+    module: &mut Module,
+    config: &Config,
+) -> Result<(Vec<GeneratedClass>, Vec<String>), String> {
+    let mut visitor = AddClassnameVisitor::new(file_path, config.clone());
+    let collected = Diagnostics::default();
+    let handler = Handler::with_emitter(true, false, Box::new(collected.clone()));
+    HANDLER.set(&handler, || module.visit_mut_with(&mut visitor));
+
+    let diagnostics: Vec<String> = collected
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(span, message)| match span {
+            Some(span) => {
+                let loc = cm.lookup_char_pos(span.lo);
+                format!(
+                    "{}:{}:{}: {}",
+                    file_path,
+                    loc.line,
+                    loc.col_display + 1,
+                    message
+                )
+            }
+            None => format!("{}: {}", file_path, message),
+        })
+        .collect();
+    if handler.has_errors() {
+        return Err(diagnostics.join("\n"));
+    }
+    Ok((visitor.generated().to_vec(), diagnostics))
+}
+
+/// Prints a single node with the SWC code generator, on a single line when
+/// `minify` is set.
+fn print<N: Node>(
+    minify: bool,
+    cm: &Lrc<SourceMap>,
+    comments: Option<&SingleThreadedComments>,
+    node: &N,
+) -> String {
+    let mut buf = vec![];
+    {
+        let mut emitter = Emitter {
+            cfg: CodegenConfig::default().with_minify(minify),
+            cm: cm.clone(),
+            comments: comments.map(|comments| comments as _),
+            wr: JsWriter::new(cm.clone(), "\n", &mut buf, None),
+        };
+        node.emit_with(&mut emitter)
+            .expect("writing to a Vec never fails");
+    }
+    String::from_utf8(buf).expect("the code generator writes UTF-8")
+}
+
+/// Parses `source`, applies `AddClassnameVisitor` and prints the result.
+/// Errors are formatted as `path:line:column: message`, and diagnostics reported
+/// as errors by the visitor fail the file.
+pub fn transform_file(
+    file_path: &str,
+    source: &str,
+    config: &Config,
+) -> Result<TransformOutput, String> {
+    let Parsed {
+        cm,
+        comments,
+        mut module,
+        ..
+    } = parse(file_path, source)?;
+
+    let original = module.clone();
+    let (generated, diagnostics) = visit(file_path, &cm, &mut module, config)?;
+    let changed = !module.eq_ignore_span(&original);
+
+    Ok(TransformOutput {
+        code: print(false, &cm, Some(&comments), &module),
+        changed,
+        generated,
+        diagnostics,
+        source_map: cm,
+    })
+}
+
+/// Like [`transform_file`], but writes the generated classes into `source` with
+/// minimal text edits instead of reprinting the module, so formatting and
+/// comments are kept. Only the `className` attributes the visitor touched are
+/// rewritten, and a manifest export is appended at the end of the file.
+pub fn eject_file(
+    file_path: &str,
+    source: &str,
+    config: &Config,
+) -> Result<TransformOutput, String> {
+    let Parsed {
+        cm, fm, mut module, ..
+    } = parse(file_path, source)?;
+
+    let original = module.clone();
+    let (generated, diagnostics) = visit(file_path, &cm, &mut module, config)?;
+
+    let before = opening_elements(&original);
+    let after = opening_elements(&module);
+    if before.len() != after.len() {
+        return Err(format!("{}: elements were added or removed", file_path));
+    }
+
+    let offset = |pos: BytePos| (pos - fm.start_pos).0 as usize;
+    let mut edits: Vec<(usize, usize, String)> = vec![];
+    for (before, after) in before.iter().zip(&after) {
+        let old = before.attrs.iter().position(is_class_name_attr);
+        let new = after.attrs.iter().position(is_class_name_attr);
+        match (old, new) {
+            (Some(old), Some(new)) => {
+                let attr = &after.attrs[new];
+                if !attr.eq_ignore_span(&before.attrs[old]) {
+                    let span = before.attrs[old].span();
+                    edits.push((offset(span.lo), offset(span.hi), print_attr(&cm, attr)));
+                }
+            }
+            (None, Some(new)) => {
+                let attr = print_attr(&cm, &after.attrs[new]);
+                let edit = match before.attrs.get(new) {
+                    Some(next) => {
+                        let mut start = offset(next.span().lo);
+                        // Spread spans start at `...`, inside the braces.
+                        if matches!(next, JSXAttrOrSpread::SpreadElement(_)) {
+                            start = source[..start].rfind('{').unwrap_or(start);
+                        }
+                        (start, start, format!("{} ", attr))
+                    }
+                    None => {
+                        let end = match (before.attrs.last(), &before.type_args) {
+                            // Spread spans end inside the braces too.
+                            (Some(last @ JSXAttrOrSpread::SpreadElement(_)), _) => {
+                                let end = offset(last.span().hi);
+                                source[end..].find('}').map_or(end, |close| end + close + 1)
+                            }
+                            (Some(last), _) => offset(last.span().hi),
+                            (None, Some(type_args)) => offset(type_args.span.hi),
+                            (None, None) => offset(before.name.span().hi),
+                        };
+                        (end, end, format!(" {}", attr))
+                    }
+                };
+                edits.push(edit);
+            }
+            (Some(old), None) => {
+                let span = before.attrs[old].span();
+                let start = source[..offset(span.lo)].trim_end().len();
+                edits.push((start, offset(span.hi), String::new()));
+            }
+            (None, None) => {}
+        }
+    }
+
+    // A manifest from an earlier run is replaced in place by the visitor.
+    let export_name = config
+        .manifest_export
+        .as_ref()
+        .and_then(|manifest| manifest.name());
+    if let Some(export_name) = export_name {
+        for (before, after) in original.body.iter().zip(&module.body) {
+            if exports_const(before, export_name) && !after.eq_ignore_span(before) {
+                let span = before.span();
+                let item = print(false, &cm, None, after);
+                edits.push((
+                    offset(span.lo),
+                    offset(span.hi),
+                    item.trim_end().to_string(),
+                ));
+            }
+        }
+    }
+
+    let mut code = source.to_string();
+    // A rewritten attribute is printed with the elements nested in it, so edits
+    // inside it are dropped. Apply the rest from the end to keep offsets valid.
+    edits.sort_by_key(|&(start, end, _)| (start, end));
+    let mut kept: Vec<(usize, usize, String)> = vec![];
+    for edit in edits {
+        if kept.last().is_none_or(|last| edit.0 >= last.1) {
+            kept.push(edit);
+        }
+    }
+    for (start, end, text) in kept.into_iter().rev() {
+        code.replace_range(start..end, &text);
+    }
+
+    for item in &module.body[original.body.len()..] {
+        if !code.ends_with('\n') {
+            code.push('\n');
+        }
+        code.push_str(&print(false, &cm, None, item));
+    }
+
+    Ok(TransformOutput {
+        changed: code != source,
+        code,
+        generated,
+        diagnostics,
+        source_map: cm,
+    })
+}
+
+/// Prints an attribute on one line. String values are printed as they are, so
+/// the quotes kept by the visitor survive.
+fn print_attr(cm: &Lrc<SourceMap>, attr: &JSXAttrOrSpread) -> String {
+    let expression = matches!(
+        attr,
+        JSXAttrOrSpread::JSXAttr(JSXAttr {
+            value: Some(JSXAttrValue::JSXExprContainer(_)),
+            ..
+        })
+    );
+    print(expression, cm, None, attr)
+}
+
+fn is_class_name_attr(attr: &JSXAttrOrSpread) -> bool {
+    matches!(
+        attr,
+        JSXAttrOrSpread::JSXAttr(JSXAttr {
+            name: JSXAttrName::Ident(ident),
+            ..
+        }) if &*ident.sym == "className"
+    )
+}
+
+/// Every opening element of `module`, in traversal order.
+fn opening_elements(module: &Module) -> Vec<JSXOpeningElement> {
+    #[derive(Default)]
+    struct Collect(Vec<JSXOpeningElement>);
+
+    impl Visit for Collect {
+        fn visit_jsx_opening_element(&mut self, n: &JSXOpeningElement) {
+            self.0.push(n.clone());
+            n.visit_children_with(self);
+        }
+    }
+
+    let mut collect = Collect::default();
+    module.visit_with(&mut collect);
+    collect.0
+}